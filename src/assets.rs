@@ -1,7 +1,7 @@
 use bevy::{
     prelude::{
-        shape, AssetServer, Assets, Color, FromWorld, Handle, Mesh, Resource, StandardMaterial,
-        World,
+        shape, AssetServer, Assets, AudioSource, Color, FromWorld, Handle, Mesh, Resource,
+        StandardMaterial, World,
     },
     text::Font,
 };
@@ -18,7 +18,6 @@ pub const HALF_VISIBLE_ROD_LENGTH: f32 = VISIBLE_ROD_LENGTH / 2.;
 pub const MOVABLE_ROD_LENGTH: f32 = DEPRECATED_AVERAGE_PILLAR_WIDTH + 1.05 * VISIBLE_ROD_LENGTH; // So that it is always visible from both sides
 pub const MOVABLE_ROD_MOVEMENT_AMPLITUDE: f32 = VISIBLE_ROD_LENGTH;
 pub const ROD_WIDTH: f32 = 0.8 * TILE_SIZE;
-pub const HALF_ROD_WIDTH: f32 = ROD_WIDTH / 2.0;
 
 pub const PYLON_HORIZONTAL_DELTA: f32 = TILE_SIZE;
 pub const PYLON_VERTICAL_MOVEMENT_AMPLITUDE: f32 = TILE_SIZE / 2.;
@@ -33,6 +32,13 @@ pub const CLIMBER_LEVITATE_DISTANCE: f32 = CLIMBER_RADIUS / 2.;
 
 const REGULAR_FONT: &str = "fonts/DancingScript-Regular.ttf";
 
+const ROD_SLIDE_SOUND: &str = "sounds/rod_slide.wav";
+const CLIMBER_HOP_SOUND: &str = "sounds/climber_hop.wav";
+const FALL_WHISTLE_SOUND: &str = "sounds/fall_whistle.wav";
+const DEATH_THUD_SOUND: &str = "sounds/death_thud.wav";
+const PYLON_CHIME_SOUND: &str = "sounds/pylon_chime.wav";
+const WIN_FANFARE_SOUND: &str = "sounds/win_fanfare.wav";
+
 #[derive(Resource)]
 pub struct GameAssets {
     pub climber_mesh: Handle<Mesh>,
@@ -43,9 +49,17 @@ pub struct GameAssets {
     pub static_rod_mat: Handle<StandardMaterial>,
     pub movable_rod_mat: Handle<StandardMaterial>,
     pub movable_rod_highlight_mat: Handle<StandardMaterial>,
+    pub movable_rod_hint_mat: Handle<StandardMaterial>,
     pub climber_mat: Handle<StandardMaterial>,
 
     pub font: Handle<Font>,
+
+    pub rod_slide_sound: Handle<AudioSource>,
+    pub climber_hop_sound: Handle<AudioSource>,
+    pub fall_whistle_sound: Handle<AudioSource>,
+    pub death_thud_sound: Handle<AudioSource>,
+    pub pylon_chime_sound: Handle<AudioSource>,
+    pub win_fanfare_sound: Handle<AudioSource>,
 }
 
 impl FromWorld for GameAssets {
@@ -95,6 +109,13 @@ impl FromWorld for GameAssets {
             emissive: Color::rgb_linear(6., 6., 3.),
             ..Default::default()
         });
+        let movable_rod_hint_mat = materials.add(StandardMaterial {
+            perceptual_roughness: 0.9,
+            metallic: 0.2,
+            base_color: Color::FUCHSIA,
+            emissive: Color::rgb_linear(11., 2., 9.),
+            ..Default::default()
+        });
         let climber_mat = materials.add(StandardMaterial {
             perceptual_roughness: 0.5,
             metallic: 0.2,
@@ -111,6 +132,12 @@ impl FromWorld for GameAssets {
             .get_resource_mut::<AssetServer>()
             .expect("Failed to get AssetServer");
         let font = asset_server.load(REGULAR_FONT);
+        let rod_slide_sound = asset_server.load(ROD_SLIDE_SOUND);
+        let climber_hop_sound = asset_server.load(CLIMBER_HOP_SOUND);
+        let fall_whistle_sound = asset_server.load(FALL_WHISTLE_SOUND);
+        let death_thud_sound = asset_server.load(DEATH_THUD_SOUND);
+        let pylon_chime_sound = asset_server.load(PYLON_CHIME_SOUND);
+        let win_fanfare_sound = asset_server.load(WIN_FANFARE_SOUND);
 
         GameAssets {
             climber_mesh,
@@ -120,8 +147,15 @@ impl FromWorld for GameAssets {
             static_rod_mat,
             movable_rod_mat,
             movable_rod_highlight_mat,
+            movable_rod_hint_mat,
             climber_mat,
             font,
+            rod_slide_sound,
+            climber_hop_sound,
+            fall_whistle_sound,
+            death_thud_sound,
+            pylon_chime_sound,
+            win_fanfare_sound,
         }
     }
 }
@@ -0,0 +1,261 @@
+//! Sound effects following the gameplay events, and background music of the levels.
+
+use bevy::{
+    audio::{Audio, AudioSink, AudioSinkPlayback, AudioSource, PlaybackSettings},
+    ecs::system::SystemParam,
+    prelude::{
+        AssetServer, Assets, DetectChanges, DetectChangesMut, EventReader, Handle, Res, ResMut,
+        Resource,
+    },
+};
+
+use crate::{
+    assets::GameAssets,
+    data::{Campaign, LevelData},
+    logic::{
+        climber::{ClimberDied, ClimberMoved, ClimberStartedFalling, PylonPowered},
+        level::{GameLevels, LevelLoaded, LevelWon},
+        rod::RodToggled,
+    },
+    settings::Settings,
+};
+
+/// Music of the current level, looping
+#[derive(Resource, Default)]
+pub struct LevelMusic {
+    /// Relative to the assets folder
+    path: Option<String>,
+    sink: Option<Handle<AudioSink>>,
+}
+
+fn play_effect(audio: &Audio, sound: &Handle<AudioSource>, settings: &Settings) {
+    if settings.effects_volume > 0. {
+        audio.play_with_settings(
+            sound.clone(),
+            PlaybackSettings::ONCE.with_volume(settings.effects_volume),
+        );
+    }
+}
+
+/// Plays the rod slide when the player toggles a rod
+pub fn play_rod_sounds(
+    audio: Res<Audio>,
+    assets: Res<GameAssets>,
+    settings: Res<Settings>,
+    mut rod_toggled_events: EventReader<RodToggled>,
+) {
+    // A single sound for rods toggled at the same time
+    if rod_toggled_events.iter().count() > 0 {
+        play_effect(&audio, &assets.rod_slide_sound, &settings);
+    }
+}
+
+/// Events played by [`play_climber_sounds`]
+#[derive(SystemParam)]
+pub struct ClimberEventReaders<'w, 's> {
+    moved: EventReader<'w, 's, ClimberMoved>,
+    started_falling: EventReader<'w, 's, ClimberStartedFalling>,
+    died: EventReader<'w, 's, ClimberDied>,
+    pylon_powered: EventReader<'w, 's, PylonPowered>,
+}
+
+/// Plays the sounds of the climbers moving to a new tile, starting to fall, dying, or powering a pylon
+pub fn play_climber_sounds(
+    audio: Res<Audio>,
+    assets: Res<GameAssets>,
+    settings: Res<Settings>,
+    mut events: ClimberEventReaders,
+) {
+    let sounds = [
+        (events.moved.iter().count(), &assets.climber_hop_sound),
+        (
+            events.started_falling.iter().count(),
+            &assets.fall_whistle_sound,
+        ),
+        (events.died.iter().count(), &assets.death_thud_sound),
+        (
+            events.pylon_powered.iter().count(),
+            &assets.pylon_chime_sound,
+        ),
+    ];
+    for (count, sound) in sounds {
+        if count > 0 {
+            play_effect(&audio, sound, &settings);
+        }
+    }
+}
+
+/// Played when the win pylon rises
+pub fn play_win_sound(
+    audio: Res<Audio>,
+    assets: Res<GameAssets>,
+    settings: Res<Settings>,
+    mut level_won_events: EventReader<LevelWon>,
+) {
+    if level_won_events.iter().count() > 0 {
+        play_effect(&audio, &assets.win_fanfare_sound, &settings);
+    }
+}
+
+/// Selects the music of a newly spawned level, if it has one
+pub fn select_level_music(
+    mut level_loaded_events: EventReader<LevelLoaded>,
+    game_levels: Res<GameLevels>,
+    campaigns: Res<Assets<Campaign>>,
+    level_assets: Res<Assets<LevelData>>,
+    mut level_music: ResMut<LevelMusic>,
+) {
+    if level_loaded_events.iter().count() == 0 {
+        return;
+    }
+    let Some(music_path) = game_levels
+        .get_current_level_data(&campaigns, &level_assets)
+        .and_then(|level_data| level_data.music.as_ref())
+    else {
+        return;
+    };
+    // Keeps playing the same music without restarting it
+    if level_music.path.as_ref() != Some(music_path) {
+        level_music.path = Some(music_path.clone());
+    }
+}
+
+/// Replaces the music playing by the selected one
+pub fn play_level_music(
+    audio: Res<Audio>,
+    audio_sinks: Res<Assets<AudioSink>>,
+    asset_server: Res<AssetServer>,
+    settings: Res<Settings>,
+    mut level_music: ResMut<LevelMusic>,
+) {
+    if !level_music.is_changed() {
+        return;
+    }
+    let Some(music_path) = level_music.path.clone() else {
+        return;
+    };
+    let level_music = level_music.bypass_change_detection();
+    if let Some(sink) = level_music
+        .sink
+        .take()
+        .and_then(|sink| audio_sinks.get(&sink))
+    {
+        sink.stop();
+    }
+    let weak_sink = audio.play_with_settings(
+        asset_server.load(music_path),
+        PlaybackSettings::LOOP.with_volume(settings.music_volume),
+    );
+    // The handle returned by play is weak, the sink would be dropped with it
+    level_music.sink = Some(audio_sinks.get_handle(weak_sink));
+}
+
+pub fn apply_music_volume(
+    settings: Res<Settings>,
+    level_music: Res<LevelMusic>,
+    audio_sinks: Res<Assets<AudioSink>>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+    if let Some(sink) = level_music
+        .sink
+        .as_ref()
+        .and_then(|sink| audio_sinks.get(sink))
+    {
+        sink.set_volume(settings.music_volume);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{
+        asset::AssetPlugin,
+        prelude::{AddAsset, App, Assets, MinimalPlugins},
+    };
+
+    use crate::{
+        data::{Campaign, CampaignLevel, LevelData, UnlockRequirement},
+        logic::level::{GameLevels, LevelLoaded},
+    };
+
+    use super::{select_level_music, LevelMusic};
+
+    /// Loads the levels of a campaign with the given musics in order, returning the music selected after each one
+    fn selected_musics(musics: &[Option<&str>]) -> Vec<Option<String>> {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugin(AssetPlugin::default())
+            .add_asset::<LevelData>()
+            .add_asset::<Campaign>()
+            .add_event::<LevelLoaded>()
+            .init_resource::<LevelMusic>()
+            .add_system(select_level_music);
+
+        let levels = musics
+            .iter()
+            .enumerate()
+            .map(|(level_idx, music)| {
+                let data = LevelData {
+                    music: music.map(String::from),
+                    ..LevelData::test_level()
+                };
+                CampaignLevel {
+                    id: level_idx.to_string(),
+                    chapter: 0,
+                    unlock: UnlockRequirement::Unlocked,
+                    data: app.world.resource_mut::<Assets<LevelData>>().add(data),
+                }
+            })
+            .collect();
+        let campaign = Campaign {
+            chapters: vec![String::from("Music")],
+            levels,
+            completes_campaign: true,
+        };
+        let campaign_handle = app
+            .world
+            .resource_mut::<Assets<Campaign>>()
+            .add(campaign.clone());
+        app.insert_resource(GameLevels::new(campaign_handle, None));
+
+        (0..musics.len())
+            .map(|level_idx| {
+                if level_idx > 0 {
+                    app.world
+                        .resource_mut::<GameLevels>()
+                        .advance_level(&campaign);
+                }
+                let level = app.world.spawn_empty().id();
+                app.world.send_event(LevelLoaded {
+                    level,
+                    level_id: level_idx.to_string(),
+                });
+                app.update();
+                app.world.resource::<LevelMusic>().path.clone()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn music_switches_to_the_track_of_the_next_level() {
+        assert_eq!(
+            selected_musics(&[Some("music/a.ogg"), Some("music/b.ogg")]),
+            vec![
+                Some(String::from("music/a.ogg")),
+                Some(String::from("music/b.ogg"))
+            ]
+        );
+    }
+
+    #[test]
+    fn music_keeps_playing_when_the_next_level_has_no_track() {
+        assert_eq!(
+            selected_musics(&[Some("music/a.ogg"), None]),
+            vec![
+                Some(String::from("music/a.ogg")),
+                Some(String::from("music/a.ogg"))
+            ]
+        );
+    }
+}
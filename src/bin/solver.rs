@@ -0,0 +1,23 @@
+//! Solves a level without starting the game, or generates a new one. Same as the `--solve` and `--generate` options
+//! of the game.
+
+use bevy_jam_3::{tools, CAMPAIGN};
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let succeeded = match args.get(1).map(String::as_str) {
+        Some("--generate") => {
+            let Some(seed) = args.get(2).and_then(|seed| seed.parse().ok()) else {
+                eprintln!("Usage: solver --generate <seed>");
+                std::process::exit(2);
+            };
+            tools::run_generator(seed)
+        }
+        Some(level) => tools::run_solver(level, CAMPAIGN),
+        None => {
+            eprintln!("Usage: solver <level file or campaign level id>");
+            std::process::exit(2);
+        }
+    };
+    std::process::exit(if succeeded { 0 } else { 1 });
+}
@@ -0,0 +1,9 @@
+//! Checks that every shipped level of the campaign loads, is valid, and can be won within its par.
+
+use bevy_jam_3::{tools, CAMPAIGN};
+
+fn main() {
+    let campaign = std::env::args().nth(1);
+    let valid = tools::run_validator(campaign.as_deref().unwrap_or(CAMPAIGN));
+    std::process::exit(if valid { 0 } else { 1 });
+}
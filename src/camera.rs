@@ -1,11 +1,12 @@
+#[cfg(feature = "fancy-graphics")]
+use bevy::{core_pipeline::bloom::BloomSettings, prelude::Entity};
 use bevy::{
-    core_pipeline::{
-        bloom::BloomSettings, clear_color::ClearColorConfig, tonemapping::Tonemapping,
-    },
+    core_pipeline::{clear_color::ClearColorConfig, tonemapping::Tonemapping},
     input::mouse::{MouseMotion, MouseScrollUnit, MouseWheel},
     prelude::{
-        default, App, Camera, Camera3d, Camera3dBundle, Commands, EventReader, EventWriter, Input,
-        KeyCode, MouseButton, Plugin, Query, Res, Transform, Vec2, Vec3,
+        default, App, Camera, Camera3d, Camera3dBundle, Commands, DetectChanges, EventReader,
+        EventWriter, Input, IntoSystemConfig, KeyCode, MouseButton, Plugin, Query, Res, Resource,
+        Transform, Vec2, Vec3, With,
     },
     time::Time,
 };
@@ -17,48 +18,73 @@ use smooth_bevy_cameras::{
 
 use crate::{
     assets::{DEPRECATED_AVERAGE_PILLAR_HEIGHT, DEPRECATED_HALF_AVERAGE_PILLAR_HEIGHT},
+    settings::Settings,
     CAMERA_CLEAR_COLOR,
 };
 
 use crate::EguiBlockInputState;
 
+/// Bounding sphere of the current level, that the camera keeps in view
+#[derive(Resource, Clone, Debug)]
+pub struct LevelFraming {
+    pub center: Vec3,
+    pub radius: f32,
+}
+
+impl Default for LevelFraming {
+    fn default() -> Self {
+        Self {
+            center: Vec3::new(0., DEPRECATED_HALF_AVERAGE_PILLAR_HEIGHT, 0.),
+            radius: 0.,
+        }
+    }
+}
+
+impl LevelFraming {
+    fn min_distance(&self, settings: &Settings) -> f32 {
+        (3. * self.radius).max(settings.min_camera_distance)
+    }
+
+    fn max_distance(&self, settings: &Settings) -> f32 {
+        self.min_distance(settings) + settings.camera_zoom_range
+    }
+}
+
 pub fn setup_camera(mut commands: Commands) {
-    commands
-        .spawn((
-            Camera3dBundle {
-                camera: Camera {
-                    hdr: true, // HDR is required for bloom
-                    ..default()
-                },
-                tonemapping: Tonemapping::ReinhardLuminance,
-                camera_3d: Camera3d {
-                    clear_color: ClearColorConfig::Custom(CAMERA_CLEAR_COLOR),
-                    ..default()
-                },
+    let mut camera = commands.spawn((
+        Camera3dBundle {
+            camera: Camera {
+                hdr: cfg!(feature = "fancy-graphics"), // HDR is required for bloom
                 ..default()
             },
-            // Enable bloom for the camera
-            BloomSettings::default(),
-            // FogSettings { // not compatible with grass plugin
-            //     color: Color::rgba(0.05, 0.05, 0.05, 1.0),
-            //     falloff: FogFalloff::Linear {
-            //         start: 5.0,
-            //         end: 20.0,
-            //     },
-            //     ..default()
-            // },
-        ))
-        .insert(OrbitCameraBundle::new(
-            OrbitCameraController {
-                mouse_translate_sensitivity: Vec2::splat(1.5),
-                mouse_rotate_sensitivity: Vec2::splat(0.2),
+            tonemapping: Tonemapping::ReinhardLuminance,
+            camera_3d: Camera3d {
+                clear_color: ClearColorConfig::Custom(CAMERA_CLEAR_COLOR),
                 ..default()
             },
+            ..default()
+        },
+        // FogSettings { // not compatible with grass plugin
+        //     color: Color::rgba(0.05, 0.05, 0.05, 1.0),
+        //     falloff: FogFalloff::Linear {
+        //         start: 5.0,
+        //         end: 20.0,
+        //     },
+        //     ..default()
+        // },
+    ));
+    // Sensitivities are set from the settings by apply_camera_settings
+    camera
+        .insert(OrbitCameraBundle::new(
+            OrbitCameraController::default(),
             Vec3::new(3.0, DEPRECATED_AVERAGE_PILLAR_HEIGHT + 2., -8.0),
             Vec3::new(0., DEPRECATED_HALF_AVERAGE_PILLAR_HEIGHT, 0.),
             Vec3::Y,
         ))
         .insert(PickingCameraBundle::default());
+    // Enable bloom for the camera
+    #[cfg(feature = "fancy-graphics")]
+    camera.insert(BloomSettings::default());
 }
 
 pub fn camera_input_map(
@@ -124,8 +150,65 @@ pub fn camera_input_map(
     events.send(orbit::ControlEvent::Zoom(scalar));
 }
 
+pub fn apply_camera_settings(
+    settings: Res<Settings>,
+    mut cameras: Query<&mut OrbitCameraController>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+    let orbit_direction = if settings.invert_orbit { -1. } else { 1. };
+    for mut controller in cameras.iter_mut() {
+        controller.mouse_rotate_sensitivity =
+            Vec2::splat(orbit_direction * settings.mouse_rotate_sensitivity);
+        controller.mouse_wheel_zoom_sensitivity = settings.mouse_wheel_zoom_sensitivity;
+    }
+}
+
+#[cfg(feature = "fancy-graphics")]
+pub fn apply_bloom_settings(
+    mut commands: Commands,
+    settings: Res<Settings>,
+    cameras: Query<Entity, With<OrbitCameraController>>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+    for camera_entity in cameras.iter() {
+        if settings.bloom {
+            commands
+                .entity(camera_entity)
+                .insert(BloomSettings::default());
+        } else {
+            commands.entity(camera_entity).remove::<BloomSettings>();
+        }
+    }
+}
+
+/// Centers the camera on a newly spawned level, keeping its orientation. Also applies the zoom limits from the settings.
+pub fn frame_level(
+    framing: Res<LevelFraming>,
+    settings: Res<Settings>,
+    mut cameras: Query<&mut LookTransform, With<OrbitCameraController>>,
+) {
+    if !framing.is_changed() && !settings.is_changed() {
+        return;
+    }
+    for mut transform in cameras.iter_mut() {
+        let look_direction = transform.look_direction().unwrap_or(Vec3::Z);
+        let radius = transform.radius().clamp(
+            framing.min_distance(&settings),
+            framing.max_distance(&settings),
+        );
+        transform.target = framing.center;
+        transform.eye = framing.center - radius * look_direction;
+    }
+}
+
 pub fn control_system(
     time: Res<Time>,
+    framing: Res<LevelFraming>,
+    settings: Res<Settings>,
     mut events: EventReader<ControlEvent>,
     mut cameras: Query<(&OrbitCameraController, &mut LookTransform, &Transform)>,
 ) {
@@ -160,7 +243,9 @@ pub fn control_system(
 
     look_angles.assert_not_looking_up();
 
-    let new_radius = (radius_scalar * transform.radius()).min(15.0).max(8.);
+    let new_radius = (radius_scalar * transform.radius())
+        .min(framing.max_distance(&settings))
+        .max(framing.min_distance(&settings));
     transform.eye = transform.target + new_radius * look_angles.unit_vector();
 }
 
@@ -186,9 +271,15 @@ impl Plugin for CustomOrbitCameraPlugin {
     fn build(&self, app: &mut App) {
         let app = app
             // .add_system(on_controller_enabled_changed.in_base_set(CoreSet::PreUpdate))
+            .init_resource::<LevelFraming>()
+            .add_system(apply_camera_settings)
+            .add_system(frame_level.before(control_system))
             .add_system(control_system)
             .add_event::<ControlEvent>();
 
         app.add_system(camera_input_map);
+
+        #[cfg(feature = "fancy-graphics")]
+        app.add_system(apply_bloom_settings);
     }
 }
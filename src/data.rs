@@ -1,31 +1,45 @@
 use std::collections::HashMap;
 
-use bevy::prelude::{AmbientLight, Color};
+use bevy::{
+    asset::{AssetLoader, AssetPath, LoadContext, LoadedAsset},
+    prelude::{AmbientLight, Color, Handle},
+    reflect::TypeUuid,
+    utils::BoxedFuture,
+};
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Copy, Debug)]
+use self::validation::InvalidLevelError;
+
+pub mod validation;
+
+pub const LEVEL_FILE_EXTENSION: &str = "level.ron";
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub enum TileDataType {
     StaticRod,
     MovableRod,
 }
 
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct TileData {
     pub i: u16,
     pub j: u16,
     pub kind: TileDataType,
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct FaceSize {
     pub w: u16,
     pub h: u16,
 }
 
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ClimberData {
     pub tile_i: u16,
     pub tile_j: u16,
 }
 
-#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum FaceDirection {
     West,
     North,
@@ -34,6 +48,23 @@ pub enum FaceDirection {
 }
 
 impl FaceDirection {
+    pub const ALL: [FaceDirection; 4] = [
+        FaceDirection::West,
+        FaceDirection::North,
+        FaceDirection::East,
+        FaceDirection::South,
+    ];
+
+    /// Index in [`FaceDirection::ALL`]
+    pub fn index(&self) -> usize {
+        match self {
+            FaceDirection::West => 0,
+            FaceDirection::North => 1,
+            FaceDirection::East => 2,
+            FaceDirection::South => 3,
+        }
+    }
+
     pub fn get_opposite(&self) -> FaceDirection {
         match self {
             FaceDirection::West => FaceDirection::East,
@@ -44,6 +75,7 @@ impl FaceDirection {
     }
 }
 
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct FaceData {
     // pub h_offset: f32,
     // pub w_offset: f32,
@@ -52,6 +84,7 @@ pub struct FaceData {
     pub climbers: Vec<ClimberData>,
 }
 
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct PillarData {
     pub x: f32,
     pub z: f32,
@@ -60,405 +93,285 @@ pub struct PillarData {
     pub faces: HashMap<FaceDirection, FaceData>,
 }
 
-// Can be serialized/deserialized
+/// Mirror of [`AmbientLight`], which does not implement serde traits.
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "AmbientLight")]
+struct AmbientLightDef {
+    color: Color,
+    brightness: f32,
+}
+
+/// Loaded from `assets/levels/*.level.ron` files, see [`LevelDataLoader`].
+#[derive(Clone, Debug, Serialize, Deserialize, TypeUuid)]
+#[uuid = "1f0a4d0e-6b6e-4d2c-9a5e-3c7c2f3f8a51"]
 pub struct LevelData {
     pub name: String,
     pub pillars: Vec<PillarData>,
     pub background_color: Color,
     pub dir_light_color: Color,
+    #[serde(with = "AmbientLightDef")]
     pub ambient_light: AmbientLight,
+    /// Rod toggles needed by a good player, found with the `solver` tool. Checked by the `validator` tool.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub par: Option<usize>,
+    /// Background music played in a loop, relative to the assets folder. Keeps the previous music when not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub music: Option<String>,
+}
+
+impl LevelData {
+    pub fn from_ron_bytes(bytes: &[u8]) -> Result<LevelData, ron::error::SpannedError> {
+        ron::de::from_bytes(bytes)
+    }
+
+    /// Same as [`LevelData::validate`], wrapped in an error with the level name
+    pub fn check(&self) -> Result<(), InvalidLevelError> {
+        let errors = self.validate();
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(InvalidLevelError {
+                level_name: self.name.clone(),
+                errors,
+            })
+        }
+    }
 }
 
-pub fn test_level_data() -> LevelData {
-    LevelData {
-        name: "Test level".to_string(),
-        pillars: vec![PillarData {
-            w: 5,
-            h: 7,
-            x: 0.,
-            z: 0.,
-            faces: HashMap::from([
-                (
-                    FaceDirection::West,
-                    FaceData {
-                        tiles: vec![
-                            TileData {
-                                i: 0,
-                                j: 0,
-                                kind: TileDataType::StaticRod,
-                            },
-                            TileData {
-                                i: 1,
-                                j: 1,
-                                kind: TileDataType::MovableRod,
-                            },
-                            TileData {
-                                i: 3,
-                                j: 3,
-                                kind: TileDataType::StaticRod,
-                            },
-                            TileData {
-                                i: 4,
-                                j: 4,
-                                kind: TileDataType::MovableRod,
-                            },
-                            TileData {
-                                i: 4,
-                                j: 6,
-                                kind: TileDataType::MovableRod,
-                            },
-                        ],
-                        climbers: vec![ClimberData {
-                            tile_i: 0,
-                            tile_j: 0,
-                        }],
-                    },
-                ),
-                (
-                    FaceDirection::East,
-                    FaceData {
-                        tiles: vec![
-                            TileData {
-                                i: 0,
-                                j: 0,
-                                kind: TileDataType::StaticRod,
-                            },
-                            TileData {
-                                i: 2,
-                                j: 2,
-                                kind: TileDataType::MovableRod,
-                            },
-                            TileData {
-                                i: 3,
-                                j: 3,
-                                kind: TileDataType::StaticRod,
-                            },
-                            TileData {
-                                i: 3,
-                                j: 5,
-                                kind: TileDataType::MovableRod,
-                            },
-                        ],
-                        climbers: vec![ClimberData {
-                            tile_i: 0,
-                            tile_j: 0,
-                        }],
-                    },
-                ),
-            ]),
-        }],
-        background_color: Color::TURQUOISE,
-        dir_light_color: Color::ORANGE,
-        ambient_light: AmbientLight {
-            color: Color::ORANGE_RED,
-            brightness: 0.2,
-        },
+/// Builder of the levels used by the unit tests
+#[cfg(test)]
+impl LevelData {
+    /// Level without any pillar yet
+    pub(crate) fn test_level() -> Self {
+        LevelData {
+            name: String::from("Test"),
+            pillars: Vec::new(),
+            background_color: Color::BLACK,
+            dir_light_color: Color::WHITE,
+            ambient_light: AmbientLight::default(),
+            par: None,
+            music: None,
+        }
+    }
+
+    /// Adds a `w`x`h` pillar without any face at (`x`, `z`)
+    pub(crate) fn with_pillar(mut self, x: f32, z: f32, w: u16, h: u16) -> Self {
+        self.pillars.push(PillarData {
+            x,
+            z,
+            w,
+            h,
+            faces: HashMap::new(),
+        });
+        self
+    }
+
+    /// Adds rods to `face` of the last added pillar
+    pub(crate) fn with_tiles(
+        mut self,
+        face: FaceDirection,
+        tiles: &[(u16, u16, TileDataType)],
+    ) -> Self {
+        self.last_pillar_face(face)
+            .tiles
+            .extend(tiles.iter().map(|&(i, j, kind)| TileData { i, j, kind }));
+        self
+    }
+
+    /// Adds climbers to `face` of the last added pillar
+    pub(crate) fn with_climbers(mut self, face: FaceDirection, climbers: &[(u16, u16)]) -> Self {
+        self.last_pillar_face(face).climbers.extend(
+            climbers
+                .iter()
+                .map(|&(tile_i, tile_j)| ClimberData { tile_i, tile_j }),
+        );
+        self
+    }
+
+    fn last_pillar_face(&mut self, face: FaceDirection) -> &mut FaceData {
+        self.pillars
+            .last_mut()
+            .expect("A pillar should be added first")
+            .faces
+            .entry(face)
+            .or_insert_with(|| FaceData {
+                tiles: Vec::new(),
+                climbers: Vec::new(),
+            })
     }
 }
 
-pub fn level_1() -> LevelData {
-    LevelData {
-        name: "I".to_string(),
-        pillars: vec![PillarData {
-            w: 5,
-            h: 7,
-            x: 0.,
-            z: 0.,
-            faces: HashMap::from([
-                (
-                    FaceDirection::West,
-                    FaceData {
-                        tiles: vec![
-                            TileData {
-                                i: 1,
-                                j: 3,
-                                kind: TileDataType::StaticRod,
-                            },
-                            TileData {
-                                i: 4,
-                                j: 6,
-                                kind: TileDataType::StaticRod,
-                            },
-                        ],
-                        climbers: vec![ClimberData {
-                            tile_i: 1,
-                            tile_j: 3,
-                        }],
-                    },
-                ),
-                (
-                    FaceDirection::East,
-                    FaceData {
-                        tiles: vec![
-                            TileData {
-                                i: 2,
-                                j: 4,
-                                kind: TileDataType::MovableRod,
-                            },
-                            TileData {
-                                i: 3,
-                                j: 5,
-                                kind: TileDataType::MovableRod,
-                            },
-                        ],
-                        climbers: vec![],
-                    },
-                ),
-            ]),
-        }],
-        background_color: Color::TURQUOISE,
-        dir_light_color: Color::ORANGE,
-        ambient_light: AmbientLight {
-            color: Color::ORANGE_RED,
-            brightness: 0.2,
-        },
+#[derive(Default)]
+pub struct LevelDataLoader;
+
+impl AssetLoader for LevelDataLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let level_data = LevelData::from_ron_bytes(bytes)?;
+            // Refuse to load a level that would not spawn correctly
+            level_data.check()?;
+            load_context.set_default_asset(LoadedAsset::new(level_data));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &[LEVEL_FILE_EXTENSION]
     }
 }
 
-pub fn level_2() -> LevelData {
-    LevelData {
-        name: "II".to_string(),
-        pillars: vec![PillarData {
-            w: 5,
-            h: 7,
-            x: 0.,
-            z: 0.,
-            faces: HashMap::from([
-                (
-                    FaceDirection::West,
-                    FaceData {
-                        tiles: vec![
-                            TileData {
-                                i: 1,
-                                j: 3,
-                                kind: TileDataType::StaticRod,
-                            },
-                            TileData {
-                                i: 3,
-                                j: 5,
-                                kind: TileDataType::MovableRod,
-                            },
-                            TileData {
-                                i: 4,
-                                j: 6,
-                                kind: TileDataType::StaticRod,
-                            },
-                        ],
-                        climbers: vec![ClimberData {
-                            tile_i: 1,
-                            tile_j: 3,
-                        }],
-                    },
-                ),
-                (
-                    FaceDirection::East,
-                    FaceData {
-                        tiles: vec![
-                            TileData {
-                                i: 2,
-                                j: 4,
-                                kind: TileDataType::MovableRod,
-                            },
-                            TileData {
-                                i: 4,
-                                j: 6,
-                                kind: TileDataType::StaticRod,
-                            },
-                        ],
-                        climbers: vec![ClimberData {
-                            tile_i: 2,
-                            tile_j: 4,
-                        }],
-                    },
-                ),
-            ]),
-        }],
-        background_color: Color::TURQUOISE,
-        dir_light_color: Color::ORANGE,
-        ambient_light: AmbientLight {
-            color: Color::ORANGE_RED,
-            brightness: 0.2,
-        },
+pub const CAMPAIGN_FILE_EXTENSION: &str = "campaign.ron";
+
+/// Condition for a campaign level to be playable
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub enum UnlockRequirement {
+    /// Playable as soon as the previous level of the campaign is completed
+    #[default]
+    PreviousLevel,
+    /// Playable from the start
+    Unlocked,
+    /// Playable once all the given levels (by id) are completed
+    Levels(Vec<String>),
+    /// Playable once all the levels of the given chapter (by name) are completed
+    Chapter(String),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CampaignLevelData {
+    pub id: String,
+    /// Relative to the campaign file
+    pub path: String,
+    #[serde(default)]
+    pub unlock: UnlockRequirement,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ChapterData {
+    pub name: String,
+    pub levels: Vec<CampaignLevelData>,
+    /// Only part of the campaign with the `dev-tools` feature
+    #[serde(default)]
+    pub debug_only: bool,
+}
+
+/// Content of a `*.campaign.ron` manifest file
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CampaignData {
+    pub chapters: Vec<ChapterData>,
+    /// When `true`, finishing the last level completes the campaign. Else the campaign loops back to its first level.
+    pub completes_campaign: bool,
+}
+
+impl CampaignData {
+    pub fn from_ron_bytes(bytes: &[u8]) -> Result<CampaignData, ron::error::SpannedError> {
+        ron::de::from_bytes(bytes)
     }
 }
 
-// Fall introduction
-pub fn level_3() -> LevelData {
-    LevelData {
-        name: "III".to_string(),
-        pillars: vec![PillarData {
-            w: 5,
-            h: 7,
-            x: 0.,
-            z: 0.,
-            faces: HashMap::from([
-                (
-                    FaceDirection::West,
-                    FaceData {
-                        tiles: vec![
-                            TileData {
-                                i: 0,
-                                j: 2,
-                                kind: TileDataType::MovableRod,
-                            },
-                            TileData {
-                                i: 2,
-                                j: 4,
-                                kind: TileDataType::StaticRod,
-                            },
-                            TileData {
-                                i: 3,
-                                j: 5,
-                                kind: TileDataType::StaticRod,
-                            },
-                            TileData {
-                                i: 4,
-                                j: 6,
-                                kind: TileDataType::StaticRod,
-                            },
-                        ],
-                        climbers: vec![ClimberData {
-                            tile_i: 0,
-                            tile_j: 2,
-                        }],
-                    },
-                ),
-                (
-                    FaceDirection::East,
-                    FaceData {
-                        tiles: vec![
-                            TileData {
-                                i: 0,
-                                j: 5,
-                                kind: TileDataType::MovableRod,
-                            },
-                            TileData {
-                                i: 1,
-                                j: 3,
-                                kind: TileDataType::MovableRod,
-                            },
-                            TileData {
-                                i: 2,
-                                j: 4,
-                                kind: TileDataType::StaticRod,
-                            },
-                            TileData {
-                                i: 3,
-                                j: 5,
-                                kind: TileDataType::StaticRod,
-                            },
-                            TileData {
-                                i: 4,
-                                j: 6,
-                                kind: TileDataType::StaticRod,
-                            },
-                        ],
-                        climbers: vec![ClimberData {
-                            tile_i: 0,
-                            tile_j: 5,
-                        }],
-                    },
-                ),
-            ]),
-        }],
-        background_color: Color::TURQUOISE,
-        dir_light_color: Color::ORANGE,
-        ambient_light: AmbientLight {
-            color: Color::ORANGE_RED,
-            brightness: 0.2,
-        },
+#[derive(Clone, Debug)]
+pub struct CampaignLevel {
+    pub id: String,
+    /// Index in [`Campaign::chapters`]
+    pub chapter: usize,
+    pub unlock: UnlockRequirement,
+    pub data: Handle<LevelData>,
+}
+
+/// Loaded from `assets/levels/*.campaign.ron` files, see [`CampaignLoader`]. Levels are flattened in play order.
+#[derive(Clone, Debug, TypeUuid)]
+#[uuid = "8d3c2b4e-2a0f-4c55-b7e9-5f6a0c9d1e27"]
+pub struct Campaign {
+    pub chapters: Vec<String>,
+    pub levels: Vec<CampaignLevel>,
+    pub completes_campaign: bool,
+}
+
+impl Campaign {
+    /// `is_completed` tells whether a level, by id, was completed
+    pub fn is_level_unlocked(&self, level_idx: usize, is_completed: impl Fn(&str) -> bool) -> bool {
+        let Some(level) = self.levels.get(level_idx) else {
+            return false;
+        };
+        match &level.unlock {
+            UnlockRequirement::PreviousLevel => match level_idx.checked_sub(1) {
+                Some(previous_idx) => is_completed(&self.levels[previous_idx].id),
+                None => true,
+            },
+            UnlockRequirement::Unlocked => true,
+            UnlockRequirement::Levels(ids) => ids.iter().all(|id| is_completed(id)),
+            UnlockRequirement::Chapter(name) => {
+                // Chapters filtered out of the build are considered completed
+                let Some(chapter) = self.chapters.iter().position(|c| c == name) else {
+                    return true;
+                };
+                self.levels
+                    .iter()
+                    .filter(|l| l.chapter == chapter)
+                    .all(|l| is_completed(&l.id))
+            }
+        }
     }
 }
 
-pub fn level_4() -> LevelData {
-    LevelData {
-        name: "IV".to_string(),
-        pillars: vec![PillarData {
-            w: 5,
-            h: 7,
-            x: 0.,
-            z: 0.,
-            faces: HashMap::from([
-                (
-                    FaceDirection::West,
-                    FaceData {
-                        tiles: vec![
-                            TileData {
-                                i: 0,
-                                j: 5,
-                                kind: TileDataType::StaticRod,
-                            },
-                            TileData {
-                                i: 1,
-                                j: 2,
-                                kind: TileDataType::MovableRod,
-                            },
-                            TileData {
-                                i: 2,
-                                j: 3,
-                                kind: TileDataType::MovableRod,
-                            },
-                        ],
-                        climbers: vec![ClimberData {
-                            tile_i: 2,
-                            tile_j: 3,
-                        }],
-                    },
-                ),
-                (
-                    FaceDirection::East,
-                    FaceData {
-                        tiles: vec![
-                            TileData {
-                                i: 1,
-                                j: 4,
-                                kind: TileDataType::MovableRod,
-                            },
-                            TileData {
-                                i: 3,
-                                j: 4,
-                                kind: TileDataType::MovableRod,
-                            },
-                            TileData {
-                                i: 1,
-                                j: 6,
-                                kind: TileDataType::MovableRod,
-                            },
-                            TileData {
-                                i: 3,
-                                j: 2,
-                                kind: TileDataType::MovableRod,
-                            },
-                            TileData {
-                                i: 3,
-                                j: 6,
-                                kind: TileDataType::StaticRod,
-                            },
-                            TileData {
-                                i: 4,
-                                j: 5,
-                                kind: TileDataType::StaticRod,
-                            },
-                        ],
-                        climbers: vec![
-                            ClimberData {
-                                tile_i: 1,
-                                tile_j: 4,
-                            },
-                            ClimberData {
-                                tile_i: 3,
-                                tile_j: 2,
-                            },
-                        ],
-                    },
-                ),
-            ]),
-        }],
-        background_color: Color::TURQUOISE,
-        dir_light_color: Color::ORANGE,
-        ambient_light: AmbientLight {
-            color: Color::ORANGE_RED,
-            brightness: 0.2,
-        },
+#[derive(Default)]
+pub struct CampaignLoader;
+
+impl AssetLoader for CampaignLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), bevy::asset::Error>> {
+        Box::pin(async move {
+            let campaign_data = CampaignData::from_ron_bytes(bytes)?;
+            let campaign_dir = load_context
+                .path()
+                .parent()
+                .map(|dir| dir.to_path_buf())
+                .unwrap_or_default();
+
+            let mut dependencies = Vec::new();
+            let mut chapters = Vec::new();
+            let mut levels = Vec::new();
+            for chapter in campaign_data
+                .chapters
+                .into_iter()
+                .filter(|chapter| cfg!(feature = "dev-tools") || !chapter.debug_only)
+            {
+                for level in chapter.levels {
+                    let level_path = AssetPath::new(campaign_dir.join(&level.path), None);
+                    levels.push(CampaignLevel {
+                        id: level.id,
+                        chapter: chapters.len(),
+                        unlock: level.unlock,
+                        data: load_context.get_handle(level_path.get_id()),
+                    });
+                    dependencies.push(level_path);
+                }
+                chapters.push(chapter.name);
+            }
+            if levels.is_empty() {
+                return Err(bevy::asset::Error::msg(
+                    "Campaign does not contain any level",
+                ));
+            }
+
+            let campaign = Campaign {
+                chapters,
+                levels,
+                completes_campaign: campaign_data.completes_campaign,
+            };
+            load_context
+                .set_default_asset(LoadedAsset::new(campaign).with_dependencies(dependencies));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &[CAMPAIGN_FILE_EXTENSION]
     }
 }
@@ -0,0 +1,433 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+};
+
+use crate::{
+    assets::TILE_SIZE,
+    simulation::{MIN_PILLAR_GAP, PILLAR_GAP_TOLERANCE},
+};
+
+use super::{FaceDirection, LevelData, PillarData, TileDataType};
+
+/// An inconsistency found in a [`LevelData`] by [`LevelData::validate`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LevelDataError {
+    NoPillar,
+    EmptyPillar {
+        pillar: usize,
+    },
+    TileOutOfBounds {
+        pillar: usize,
+        face: FaceDirection,
+        i: u16,
+        j: u16,
+    },
+    DuplicateTile {
+        pillar: usize,
+        face: FaceDirection,
+        i: u16,
+        j: u16,
+    },
+    ClimberOutOfBounds {
+        pillar: usize,
+        face: FaceDirection,
+        i: u16,
+        j: u16,
+    },
+    ClimberNotOnRod {
+        pillar: usize,
+        face: FaceDirection,
+        i: u16,
+        j: u16,
+    },
+    ClimbersOnSameTile {
+        pillar: usize,
+        face: FaceDirection,
+        i: u16,
+        j: u16,
+    },
+    /// Pylons are spread on top of a face, one per climber and one per column at most
+    TooManyClimbers {
+        pillar: usize,
+        face: FaceDirection,
+        climbers: usize,
+        pylons: usize,
+    },
+    /// A movable rod would slide into a rod of the opposite face
+    MovableRodOppositeRod {
+        pillar: usize,
+        face: FaceDirection,
+        i: u16,
+        j: u16,
+    },
+    /// Two pillars overlap, or face each other closer than [`MIN_PILLAR_GAP`]
+    PillarsTooClose {
+        pillar: usize,
+        other_pillar: usize,
+    },
+}
+
+impl fmt::Display for LevelDataError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LevelDataError::NoPillar => write!(f, "the level does not have any pillar"),
+            LevelDataError::EmptyPillar { pillar } => {
+                write!(f, "pillar {pillar} has a null width or height")
+            }
+            LevelDataError::TileOutOfBounds { pillar, face, i, j } => write!(
+                f,
+                "pillar {pillar}, {face:?} face: tile ({i}, {j}) is out of the face bounds"
+            ),
+            LevelDataError::DuplicateTile { pillar, face, i, j } => write!(
+                f,
+                "pillar {pillar}, {face:?} face: tile ({i}, {j}) is defined more than once"
+            ),
+            LevelDataError::ClimberOutOfBounds { pillar, face, i, j } => write!(
+                f,
+                "pillar {pillar}, {face:?} face: climber on ({i}, {j}) is out of the face bounds"
+            ),
+            LevelDataError::ClimberNotOnRod { pillar, face, i, j } => write!(
+                f,
+                "pillar {pillar}, {face:?} face: climber on ({i}, {j}) does not start on a rod"
+            ),
+            LevelDataError::ClimbersOnSameTile { pillar, face, i, j } => write!(
+                f,
+                "pillar {pillar}, {face:?} face: more than one climber on ({i}, {j})"
+            ),
+            LevelDataError::TooManyClimbers {
+                pillar,
+                face,
+                climbers,
+                pylons,
+            } => write!(
+                f,
+                "pillar {pillar}, {face:?} face: {climbers} climbers for only {pylons} pylons"
+            ),
+            LevelDataError::MovableRodOppositeRod { pillar, face, i, j } => write!(
+                f,
+                "pillar {pillar}, {face:?} face: movable rod on ({i}, {j}) faces a rod on the opposite face"
+            ),
+            LevelDataError::PillarsTooClose {
+                pillar,
+                other_pillar,
+            } => write!(
+                f,
+                "pillars {pillar} and {other_pillar} are less than {MIN_PILLAR_GAP} apart"
+            ),
+        }
+    }
+}
+
+/// Returned when a level file does not pass [`LevelData::validate`]
+#[derive(Clone, Debug)]
+pub struct InvalidLevelError {
+    pub level_name: String,
+    pub errors: Vec<LevelDataError>,
+}
+
+impl fmt::Display for InvalidLevelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid level \"{}\":", self.level_name)?;
+        for error in self.errors.iter() {
+            write!(f, "\n - {error}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for InvalidLevelError {}
+
+impl LevelData {
+    /// Returns every inconsistency found in the level, an empty list means the level can be spawned
+    pub fn validate(&self) -> Vec<LevelDataError> {
+        let mut errors = Vec::new();
+        if self.pillars.is_empty() {
+            errors.push(LevelDataError::NoPillar);
+        }
+
+        for (pillar_idx, pillar) in self.pillars.iter().enumerate() {
+            if pillar.w == 0 || pillar.h == 0 {
+                errors.push(LevelDataError::EmptyPillar { pillar: pillar_idx });
+                continue;
+            }
+
+            let mut rods: HashMap<FaceDirection, HashMap<(u16, u16), TileDataType>> =
+                HashMap::new();
+            for (face_direction, face) in pillar.faces.iter() {
+                let face_rods = rods.entry(face_direction.clone()).or_default();
+                for tile in face.tiles.iter() {
+                    if tile.i >= pillar.w || tile.j >= pillar.h {
+                        errors.push(LevelDataError::TileOutOfBounds {
+                            pillar: pillar_idx,
+                            face: face_direction.clone(),
+                            i: tile.i,
+                            j: tile.j,
+                        });
+                    } else if face_rods.insert((tile.i, tile.j), tile.kind).is_some() {
+                        errors.push(LevelDataError::DuplicateTile {
+                            pillar: pillar_idx,
+                            face: face_direction.clone(),
+                            i: tile.i,
+                            j: tile.j,
+                        });
+                    }
+                }
+            }
+
+            for (face_direction, face) in pillar.faces.iter() {
+                let face_rods = &rods[face_direction];
+                let opposite_face_rods = rods.get(&face_direction.get_opposite());
+                for (&(i, j), kind) in face_rods.iter() {
+                    if let TileDataType::MovableRod = kind {
+                        if opposite_face_rods.is_some_and(|rods| rods.contains_key(&(i, j))) {
+                            errors.push(LevelDataError::MovableRodOppositeRod {
+                                pillar: pillar_idx,
+                                face: face_direction.clone(),
+                                i,
+                                j,
+                            });
+                        }
+                    }
+                }
+
+                if face.climbers.len() > pillar.w as usize {
+                    errors.push(LevelDataError::TooManyClimbers {
+                        pillar: pillar_idx,
+                        face: face_direction.clone(),
+                        climbers: face.climbers.len(),
+                        pylons: pillar.w as usize,
+                    });
+                }
+
+                let mut occupied_tiles = HashSet::new();
+                for climber in face.climbers.iter() {
+                    let (i, j) = (climber.tile_i, climber.tile_j);
+                    if i >= pillar.w || j >= pillar.h {
+                        errors.push(LevelDataError::ClimberOutOfBounds {
+                            pillar: pillar_idx,
+                            face: face_direction.clone(),
+                            i,
+                            j,
+                        });
+                        continue;
+                    }
+                    if !face_rods.contains_key(&(i, j)) {
+                        errors.push(LevelDataError::ClimberNotOnRod {
+                            pillar: pillar_idx,
+                            face: face_direction.clone(),
+                            i,
+                            j,
+                        });
+                    }
+                    if !occupied_tiles.insert((i, j)) {
+                        errors.push(LevelDataError::ClimbersOnSameTile {
+                            pillar: pillar_idx,
+                            face: face_direction.clone(),
+                            i,
+                            j,
+                        });
+                    }
+                }
+            }
+        }
+
+        for (pillar_idx, pillar) in self.pillars.iter().enumerate() {
+            for (other_idx, other) in self.pillars.iter().enumerate().skip(pillar_idx + 1) {
+                if pillars_too_close(pillar, other) {
+                    errors.push(LevelDataError::PillarsTooClose {
+                        pillar: pillar_idx,
+                        other_pillar: other_idx,
+                    });
+                }
+            }
+        }
+        errors
+    }
+}
+
+/// `true` when the pillars overlap, or when their extents overlap on one axis and they are closer than
+/// [`MIN_PILLAR_GAP`] on the other one. Diagonal neighbours don't face each other and can be closer.
+fn pillars_too_close(pillar: &PillarData, other: &PillarData) -> bool {
+    let half_widths = (pillar.w + other.w) as f32 * TILE_SIZE / 2.;
+    let gap_x = (pillar.x - other.x).abs() - half_widths;
+    let gap_z = (pillar.z - other.z).abs() - half_widths;
+    let min_gap = MIN_PILLAR_GAP - PILLAR_GAP_TOLERANCE;
+    (gap_x < min_gap && gap_z < 0.) || (gap_z < min_gap && gap_x < 0.)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        assets::TILE_SIZE,
+        data::{FaceDirection, LevelData, TileDataType},
+        simulation::MIN_PILLAR_GAP,
+    };
+
+    use super::LevelDataError;
+
+    /// A 4x4 pillar with a static rod in (1, 0) and a climber on it, on its West face
+    fn level() -> LevelData {
+        LevelData::test_level()
+            .with_pillar(0., 0., 4, 4)
+            .with_tiles(FaceDirection::West, &[(1, 0, TileDataType::StaticRod)])
+            .with_climbers(FaceDirection::West, &[(1, 0)])
+    }
+
+    #[test]
+    fn valid_level_has_no_error() {
+        assert_eq!(level().validate(), vec![]);
+    }
+
+    #[test]
+    fn level_without_pillar() {
+        assert_eq!(
+            LevelData::test_level().validate(),
+            vec![LevelDataError::NoPillar]
+        );
+    }
+
+    #[test]
+    fn empty_pillar() {
+        let mut level = level();
+        level.pillars[0].h = 0;
+        assert_eq!(
+            level.validate(),
+            vec![LevelDataError::EmptyPillar { pillar: 0 }]
+        );
+    }
+
+    #[test]
+    fn tile_out_of_bounds() {
+        let level = level().with_tiles(FaceDirection::West, &[(4, 2, TileDataType::StaticRod)]);
+        assert_eq!(
+            level.validate(),
+            vec![LevelDataError::TileOutOfBounds {
+                pillar: 0,
+                face: FaceDirection::West,
+                i: 4,
+                j: 2
+            }]
+        );
+    }
+
+    #[test]
+    fn duplicate_tile() {
+        let level = level().with_tiles(FaceDirection::West, &[(1, 0, TileDataType::MovableRod)]);
+        assert_eq!(
+            level.validate(),
+            vec![LevelDataError::DuplicateTile {
+                pillar: 0,
+                face: FaceDirection::West,
+                i: 1,
+                j: 0
+            }]
+        );
+    }
+
+    #[test]
+    fn climber_out_of_bounds() {
+        let level = level().with_climbers(FaceDirection::West, &[(0, 4)]);
+        assert_eq!(
+            level.validate(),
+            vec![LevelDataError::ClimberOutOfBounds {
+                pillar: 0,
+                face: FaceDirection::West,
+                i: 0,
+                j: 4
+            }]
+        );
+    }
+
+    #[test]
+    fn climber_not_on_rod() {
+        let level = level().with_climbers(FaceDirection::West, &[(2, 0)]);
+        assert_eq!(
+            level.validate(),
+            vec![LevelDataError::ClimberNotOnRod {
+                pillar: 0,
+                face: FaceDirection::West,
+                i: 2,
+                j: 0
+            }]
+        );
+    }
+
+    #[test]
+    fn climbers_on_same_tile() {
+        let level = level().with_climbers(FaceDirection::West, &[(1, 0)]);
+        assert_eq!(
+            level.validate(),
+            vec![LevelDataError::ClimbersOnSameTile {
+                pillar: 0,
+                face: FaceDirection::West,
+                i: 1,
+                j: 0
+            }]
+        );
+    }
+
+    #[test]
+    fn too_many_climbers() {
+        let tiles: Vec<_> = (0..4).map(|i| (i, 1, TileDataType::StaticRod)).collect();
+        let climbers: Vec<_> = (0..4).map(|i| (i, 1)).collect();
+        let level = level()
+            .with_tiles(FaceDirection::West, &tiles)
+            .with_climbers(FaceDirection::West, &climbers);
+        assert_eq!(
+            level.validate(),
+            vec![LevelDataError::TooManyClimbers {
+                pillar: 0,
+                face: FaceDirection::West,
+                climbers: 5,
+                pylons: 4
+            }]
+        );
+    }
+
+    #[test]
+    fn movable_rod_opposite_rod() {
+        let level = level()
+            .with_tiles(FaceDirection::West, &[(2, 1, TileDataType::MovableRod)])
+            .with_tiles(FaceDirection::East, &[(2, 1, TileDataType::StaticRod)]);
+        assert_eq!(
+            level.validate(),
+            vec![LevelDataError::MovableRodOppositeRod {
+                pillar: 0,
+                face: FaceDirection::West,
+                i: 2,
+                j: 1
+            }]
+        );
+    }
+
+    #[test]
+    fn pillars_too_close() {
+        // Rods sticking out of the facing faces would meet in the middle of the gap
+        let x = (4. * TILE_SIZE + MIN_PILLAR_GAP - TILE_SIZE) / 2.;
+        let level = LevelData::test_level()
+            .with_pillar(-x, 0., 4, 4)
+            .with_pillar(x, 1., 4, 4);
+        assert_eq!(
+            level.validate(),
+            vec![LevelDataError::PillarsTooClose {
+                pillar: 0,
+                other_pillar: 1
+            }]
+        );
+    }
+
+    #[test]
+    fn pillars_at_the_minimum_gap_or_diagonal() {
+        let x = (4. * TILE_SIZE + MIN_PILLAR_GAP) / 2.;
+        let level = LevelData::test_level()
+            .with_pillar(-x, 0., 4, 4)
+            .with_pillar(x, 1., 4, 4);
+        assert_eq!(level.validate(), vec![]);
+        // Corner to corner, no face faces the other pillar
+        let level = LevelData::test_level()
+            .with_pillar(0., 0., 4, 4)
+            .with_pillar(4. * TILE_SIZE, 4. * TILE_SIZE, 4, 4);
+        assert_eq!(level.validate(), vec![]);
+    }
+}
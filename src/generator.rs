@@ -0,0 +1,322 @@
+//! Seeded procedural generation of single-pillar levels.
+//!
+//! Candidates are drawn at random from a [`GeneratorParams`] and kept only when the [`crate::solver`] can win them. The
+//! same seed and parameters always produce the same level.
+
+use std::{collections::HashMap, fmt};
+
+use bevy::prelude::{AmbientLight, Color};
+use rand::{seq::SliceRandom, Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::{
+    data::{ClimberData, FaceData, FaceDirection, LevelData, PillarData, TileData, TileDataType},
+    simulation::Simulation,
+    solver::{solve, Solution, SolverOptions, SolverResult},
+};
+
+#[derive(Clone, Debug)]
+pub struct GeneratorParams {
+    pub pillar_w: u16,
+    pub pillar_h: u16,
+    /// Faces on which rods and climbers may be placed
+    pub faces: Vec<FaceDirection>,
+    pub climbers: usize,
+    /// Static rods added at random, on top of the ones of the climbers routes
+    pub static_rods: usize,
+    /// Maximum count of movable rods, placed so that they have to be toggled
+    pub movable_rods: usize,
+    /// Number of toggles of the shortest solution
+    pub difficulty: usize,
+    /// Candidates drawn before settling for the solvable candidate closest to [`GeneratorParams::difficulty`]
+    pub max_attempts: u32,
+    /// Candidates needing more states than this to be solved are rejected
+    pub max_solver_states: usize,
+}
+
+impl Default for GeneratorParams {
+    fn default() -> Self {
+        Self {
+            pillar_w: 5,
+            pillar_h: 7,
+            faces: FaceDirection::ALL.to_vec(),
+            climbers: 2,
+            static_rods: 3,
+            movable_rods: 4,
+            difficulty: 3,
+            max_attempts: 200,
+            max_solver_states: 20_000,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct GeneratedLevel {
+    pub level: LevelData,
+    pub solution: Solution,
+}
+
+/// Random tiles drawn for each climber before giving up on finding it a free one
+const MAX_CLIMBER_PLACEMENT_TRIES: u32 = 1_000;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum GeneratorError {
+    /// The parameters ask for more climbers than the pillar faces can hold, or for a pillar too small to climb
+    InvalidParams,
+    /// No free tile was found for a climber
+    NoRoomForClimbers,
+    /// None of the candidates was solvable
+    NoSolvableCandidate { attempts: u32 },
+}
+
+impl fmt::Display for GeneratorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GeneratorError::InvalidParams => {
+                write!(f, "the pillar is too small for the requested climbers")
+            }
+            GeneratorError::NoRoomForClimbers => {
+                write!(f, "no free tile left for the requested climbers")
+            }
+            GeneratorError::NoSolvableCandidate { attempts } => {
+                write!(f, "no solvable level found in {attempts} attempts")
+            }
+        }
+    }
+}
+
+impl std::error::Error for GeneratorError {}
+
+/// Generates the level identified by `seed`, named after it
+pub fn generate_level(
+    seed: u64,
+    params: &GeneratorParams,
+) -> Result<GeneratedLevel, GeneratorError> {
+    // Listing a face twice would only skew the random draws
+    let mut faces: Vec<FaceDirection> = Vec::new();
+    for face in params.faces.iter() {
+        if !faces.contains(face) {
+            faces.push(face.clone());
+        }
+    }
+    let params = &GeneratorParams {
+        faces,
+        ..params.clone()
+    };
+    if params.faces.is_empty()
+        || params.pillar_w < 2
+        || params.pillar_h < 2
+        || params.climbers > params.faces.len() * params.pillar_w as usize
+    {
+        return Err(GeneratorError::InvalidParams);
+    }
+
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    let solver_options = SolverOptions {
+        max_states: params.max_solver_states,
+        ..Default::default()
+    };
+    let mut best: Option<GeneratedLevel> = None;
+    for _ in 0..params.max_attempts {
+        let mut level = generate_candidate(&mut rng, seed, params)?;
+        if !level.validate().is_empty() {
+            continue;
+        }
+        let SolverResult::Solved(solution) = solve(&Simulation::new(&level), &solver_options)
+        else {
+            continue;
+        };
+        // Already won without any action
+        if solution.actions.is_empty() {
+            continue;
+        }
+        level.par = Some(solution.actions.len());
+
+        let distance = solution.actions.len().abs_diff(params.difficulty);
+        if distance == 0 {
+            return Ok(GeneratedLevel { level, solution });
+        }
+        let is_closer = match &best {
+            Some(best) => distance < best.solution.actions.len().abs_diff(params.difficulty),
+            None => true,
+        };
+        if is_closer {
+            best = Some(GeneratedLevel { level, solution });
+        }
+    }
+    best.ok_or(GeneratorError::NoSolvableCandidate {
+        attempts: params.max_attempts,
+    })
+}
+
+fn generate_candidate(
+    rng: &mut ChaCha8Rng,
+    seed: u64,
+    params: &GeneratorParams,
+) -> Result<LevelData, GeneratorError> {
+    let (w, h) = (params.pillar_w, params.pillar_h);
+    let mut faces: HashMap<FaceDirection, FaceData> = params
+        .faces
+        .iter()
+        .map(|face| {
+            (
+                face.clone(),
+                FaceData {
+                    tiles: Vec::new(),
+                    climbers: Vec::new(),
+                },
+            )
+        })
+        .collect();
+    let is_free = |faces: &HashMap<FaceDirection, FaceData>, face: &FaceDirection, i, j| {
+        !faces
+            .get(face)
+            .is_some_and(|face| face.tiles.iter().any(|t| t.i == i && t.j == j))
+    };
+    let add_tile = |faces: &mut HashMap<FaceDirection, FaceData>, face, i, j, kind| {
+        faces
+            .entry(face)
+            .or_insert_with(|| FaceData {
+                tiles: Vec::new(),
+                climbers: Vec::new(),
+            })
+            .tiles
+            .push(TileData { i, j, kind });
+    };
+
+    // Each climber gets a route of rods up to the top row. Some of those rods start on the opposite face, and some rods
+    // that would lead the climber astray start on its face, so that they have to be toggled.
+    let mut route_tiles = Vec::new();
+    let mut diverting_tiles = Vec::new();
+    let mut placed_climbers = 0;
+    let mut placement_tries = 0;
+    while placed_climbers < params.climbers {
+        placement_tries += 1;
+        if placement_tries > MAX_CLIMBER_PLACEMENT_TRIES {
+            return Err(GeneratorError::NoRoomForClimbers);
+        }
+        let face = params.faces.choose(rng).unwrap().clone();
+        let (mut i, j) = (
+            rng.gen_range(0..w),
+            rng.gen_range(0..h.saturating_sub(1).max(1)),
+        );
+        if faces[&face].climbers.len() >= w as usize || !is_free(&faces, &face, i, j) {
+            continue;
+        }
+        add_tile(&mut faces, face.clone(), i, j, TileDataType::StaticRod);
+        faces.get_mut(&face).unwrap().climbers.push(ClimberData {
+            tile_i: i,
+            tile_j: j,
+        });
+        placed_climbers += 1;
+        placement_tries = 0;
+
+        for j in j + 1..h {
+            // Climbers try to move towards i + 1 first
+            let next_i = if i + 1 < w && (i == 0 || rng.gen_bool(0.5)) {
+                i + 1
+            } else if i > 0 {
+                diverting_tiles.push((face.clone(), i + 1, j));
+                i - 1
+            } else {
+                break;
+            };
+            i = next_i;
+            if !route_tiles.contains(&(face.clone(), i, j)) {
+                route_tiles.push((face.clone(), i, j));
+            }
+        }
+    }
+    diverting_tiles.retain(|(_, i, _)| *i < w);
+    diverting_tiles.retain(|tile| !route_tiles.contains(tile));
+
+    let mut placed_movable_rods = 0;
+    route_tiles.shuffle(rng);
+    diverting_tiles.shuffle(rng);
+    for (face, i, j) in route_tiles {
+        let opposite_face = face.get_opposite();
+        if !is_free(&faces, &face, i, j) {
+            continue;
+        }
+        if placed_movable_rods < params.movable_rods
+            && is_free(&faces, &opposite_face, i, j)
+            && rng.gen_bool(0.5)
+        {
+            add_tile(&mut faces, opposite_face, i, j, TileDataType::MovableRod);
+            placed_movable_rods += 1;
+        } else {
+            add_tile(&mut faces, face, i, j, TileDataType::StaticRod);
+        }
+    }
+    for (face, i, j) in diverting_tiles {
+        if placed_movable_rods < params.movable_rods
+            && is_free(&faces, &face, i, j)
+            && is_free(&faces, &face.get_opposite(), i, j)
+        {
+            add_tile(&mut faces, face, i, j, TileDataType::MovableRod);
+            placed_movable_rods += 1;
+        }
+    }
+
+    // Additional rods, which may open other routes
+    for _ in 0..params.static_rods {
+        let face = params.faces.choose(rng).unwrap().clone();
+        let (i, j) = (rng.gen_range(0..w), rng.gen_range(0..h));
+        if is_free(&faces, &face, i, j) && is_free(&faces, &face.get_opposite(), i, j) {
+            add_tile(&mut faces, face, i, j, TileDataType::StaticRod);
+        }
+    }
+
+    Ok(LevelData {
+        name: format!("#{seed}"),
+        pillars: vec![PillarData {
+            x: 0.,
+            z: 0.,
+            w,
+            h,
+            faces,
+        }],
+        background_color: Color::rgb(0.25, 0.88, 0.82),
+        dir_light_color: Color::ORANGE,
+        ambient_light: AmbientLight {
+            color: Color::rgb(1.0, 0.27, 0.0),
+            brightness: 0.2,
+        },
+        par: None,
+        music: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{data::FaceDirection, simulation::Simulation};
+
+    use super::{generate_level, GeneratorError, GeneratorParams};
+
+    #[test]
+    fn same_seed_generates_the_same_level() {
+        let params = GeneratorParams::default();
+        let first = generate_level(42, &params).unwrap();
+        let second = generate_level(42, &params).unwrap();
+        // The faces of the level data are in a `HashMap`, the simulation orders them
+        assert_eq!(
+            Simulation::new(&first.level),
+            Simulation::new(&second.level)
+        );
+        assert_eq!(first.level.par, second.level.par);
+        assert_eq!(first.solution, second.solution);
+    }
+
+    #[test]
+    fn duplicate_faces_do_not_make_room_for_more_climbers() {
+        let params = GeneratorParams {
+            faces: vec![FaceDirection::West, FaceDirection::West],
+            climbers: GeneratorParams::default().pillar_w as usize + 1,
+            ..Default::default()
+        };
+        assert_eq!(
+            generate_level(42, &params).unwrap_err(),
+            GeneratorError::InvalidParams
+        );
+    }
+}
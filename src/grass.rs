@@ -1,5 +1,8 @@
 use bevy::{
-    prelude::{default, Assets, Color, Commands, Mesh, Name, ResMut, Vec2, Vec3},
+    prelude::{
+        default, Assets, Color, Commands, DetectChanges, Mesh, Name, Query, Res, ResMut, Vec2,
+        Vec3, Visibility, With,
+    },
     render::{mesh::Indices, render_resource::PrimitiveTopology},
 };
 
@@ -10,6 +13,8 @@ use warbler_grass::{
     GrassConfiguration,
 };
 
+use crate::settings::Settings;
+
 fn custom_grass_mesh() -> Mesh {
     let mut grass_mesh = Mesh::new(PrimitiveTopology::TriangleList);
     grass_mesh.insert_attribute(
@@ -64,3 +69,18 @@ pub fn setup_grass(mut commands: Commands, mut meshes: ResMut<Assets<Mesh>>) {
         Name::new("Grass"),
     ));
 }
+
+pub fn apply_grass_settings(
+    settings: Res<Settings>,
+    mut grass: Query<&mut Visibility, With<Grass>>,
+) {
+    if settings.is_changed() {
+        for mut visibility in grass.iter_mut() {
+            *visibility = if settings.grass {
+                Visibility::Inherited
+            } else {
+                Visibility::Hidden
+            };
+        }
+    }
+}
@@ -0,0 +1,311 @@
+//! The game rules without window nor renderer, advanced one fixed tick at a time. Used by the tests to play the
+//! levels.
+//!
+//! Every [`HeadlessGame::advance_ticks`] frame runs the schedules in the same order as the game: state transitions,
+//! then exactly one [`CoreSchedule::FixedUpdate`] tick, then the update systems.
+
+use std::time::Instant;
+
+use bevy::{
+    asset::AssetPlugin,
+    ecs::system::SystemState,
+    prelude::{
+        AddAsset, App, Mesh, MinimalPlugins, Mut, NextState, StandardMaterial, State, World,
+    },
+    time::{fixed_timestep::FixedTime, TimeUpdateStrategy},
+};
+
+use crate::{
+    assets::GameAssets,
+    logic::{
+        climber::ClimberPlugin,
+        history::{LevelHistory, SimulationRestored},
+        level::{GameLevels, LevelCompletion, LevelEvent, LevelPlugin, LevelSimulation},
+        rod::{RodPlugin, RodToggles},
+    },
+    replay::ReplayPlayback,
+    simulation::{Action, Simulation},
+    solver::TimedAction,
+    tools::load_level_from_disk,
+    GameState, CAMPAIGN,
+};
+
+/// Campaign level id of the single level of a headless game
+const HEADLESS_LEVEL_ID: &str = "headless";
+
+pub struct HeadlessGame {
+    app: App,
+    /// Time of the last frame, advanced by exactly one fixed tick per frame
+    frame_instant: Instant,
+}
+
+impl HeadlessGame {
+    /// `level` is either a path to a level file or a campaign level id, see [`load_level_from_disk`]. The game starts
+    /// playing it right away.
+    pub fn new(level: &str) -> Result<Self, String> {
+        let level_data = load_level_from_disk(level, CAMPAIGN)?;
+
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_plugin(AssetPlugin::default())
+            .add_asset::<Mesh>()
+            .add_asset::<StandardMaterial>()
+            .init_resource::<GameAssets>()
+            .add_plugin(LevelPlugin)
+            .add_plugin(ClimberPlugin)
+            .add_plugin(RodPlugin);
+
+        let game_levels = GameLevels::single_level(&mut app.world, HEADLESS_LEVEL_ID, level_data);
+        app.insert_resource(game_levels)
+            .insert_resource(NextState(Some(GameState::Playing)));
+
+        let mut game = Self {
+            app,
+            frame_instant: Instant::now(),
+        };
+        // Spawns the level
+        game.update();
+        Ok(game)
+    }
+
+    /// Id of the level in the campaign of the headless game, whatever the level it was created from
+    pub fn level_id(&self) -> &'static str {
+        HEADLESS_LEVEL_ID
+    }
+
+    fn update(&mut self) {
+        self.app
+            .insert_resource(TimeUpdateStrategy::ManualInstant(self.frame_instant));
+        self.app.update();
+    }
+
+    /// Runs `ticks` frames of a single fixed tick each
+    pub fn advance_ticks(&mut self, ticks: u64) {
+        for _ in 0..ticks {
+            self.frame_instant += self.app.world.resource::<FixedTime>().period;
+            self.update();
+        }
+    }
+
+    /// Advances to `tick`, stopping early when the level is won or lost
+    pub fn advance_to_tick(&mut self, tick: u64) {
+        while self.simulation().tick() < tick && self.state() == GameState::Playing {
+            self.advance_ticks(1);
+        }
+    }
+
+    /// Applies `actions` at their tick, as a player clicking the rods, then lets the level run until it is won or lost,
+    /// or `max_tick` is reached. Returns `false` when an action was refused.
+    pub fn play(&mut self, actions: &[TimedAction], max_tick: u64) -> bool {
+        for timed_action in actions {
+            self.advance_to_tick(timed_action.tick);
+            if self.simulation().tick() != timed_action.tick || !self.apply(&timed_action.action) {
+                return false;
+            }
+        }
+        self.advance_to_tick(max_tick);
+        true
+    }
+
+    /// Toggles a rod the way the player does. Returns `false` when the toggle was refused.
+    pub fn apply(&mut self, action: &Action) -> bool {
+        let world = &mut self.app.world;
+        let mut system_state: SystemState<RodToggles> = SystemState::new(world);
+        let applied = system_state.get_mut(world).apply(action);
+        system_state.apply(world);
+        applied
+    }
+
+    /// Undoes the last rod toggle the way the player does, the level entities following on a frame without any fixed
+    /// tick. Returns `false` when there was nothing to undo.
+    pub fn undo(&mut self) -> bool {
+        let undone = self
+            .app
+            .world
+            .resource_scope(|world, mut history: Mut<LevelHistory>| {
+                history.undo(&mut world.resource_mut::<LevelSimulation>().0)
+            });
+        if undone {
+            self.app.world.send_event(SimulationRestored);
+            self.update();
+        }
+        undone
+    }
+
+    /// Starts playing `playback` back from the start of the level
+    pub fn start_playback(&mut self, playback: ReplayPlayback) {
+        self.app.insert_resource(playback);
+        self.app.world.send_event(LevelEvent::Reload);
+        self.update();
+    }
+
+    pub fn state(&self) -> GameState {
+        self.app.world.resource::<State<GameState>>().0.clone()
+    }
+
+    pub fn simulation(&self) -> &Simulation {
+        &self.app.world.resource::<LevelSimulation>().0
+    }
+
+    pub fn level_completion(&self) -> &LevelCompletion {
+        self.app.world.resource::<LevelCompletion>()
+    }
+
+    pub fn world(&self) -> &World {
+        &self.app.world
+    }
+
+    pub fn world_mut(&mut self) -> &mut World {
+        &mut self.app.world
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        data::FaceDirection,
+        logic::history::LevelHistory,
+        replay::{Replay, ReplayPlayback},
+        simulation::{Action, ClimberState, TilePosition},
+        solver::TimedAction,
+        GameState,
+    };
+
+    use super::HeadlessGame;
+
+    /// Gives up on levels not won nor lost after 30 seconds
+    const MAX_TICK: u64 = 30 * 60;
+
+    fn toggle(tick: u64, pillar: usize, face: FaceDirection, i: u16, j: u16) -> TimedAction {
+        TimedAction {
+            tick,
+            action: Action::ToggleRod {
+                pillar,
+                face,
+                position: TilePosition { i, j },
+            },
+        }
+    }
+
+    fn assert_wins(level: &str, solution: &[TimedAction]) {
+        let mut game = HeadlessGame::new(level).unwrap();
+        assert!(game.play(solution, MAX_TICK), "{level}: action refused");
+        // The state changes on the frame following the win
+        game.advance_ticks(1);
+        assert_eq!(game.state(), GameState::Won, "{level}");
+        assert!(game.level_completion().is_won(), "{level}");
+        assert!(game
+            .simulation()
+            .climbers()
+            .iter()
+            .all(|climber| matches!(climber.state, ClimberState::Saved { .. })));
+    }
+
+    #[test]
+    fn level_1_solution_wins() {
+        use FaceDirection::*;
+        assert_wins("I", &[toggle(0, 0, East, 2, 4), toggle(0, 0, East, 3, 5)]);
+    }
+
+    #[test]
+    fn level_2_solution_wins() {
+        use FaceDirection::*;
+        assert_wins(
+            "II",
+            &[
+                toggle(0, 0, West, 3, 5),
+                toggle(6, 0, East, 2, 4),
+                toggle(60, 0, East, 3, 5),
+            ],
+        );
+    }
+
+    #[test]
+    fn level_3_solution_wins() {
+        use FaceDirection::*;
+        assert_wins(
+            "III",
+            &[
+                toggle(0, 0, East, 0, 5),
+                toggle(0, 0, East, 1, 3),
+                toggle(6, 0, West, 0, 2),
+                toggle(60, 0, West, 1, 3),
+            ],
+        );
+    }
+
+    #[test]
+    fn level_4_solution_wins() {
+        use FaceDirection::*;
+        assert_wins(
+            "IV",
+            &[
+                toggle(0, 0, West, 1, 2),
+                toggle(0, 0, East, 1, 4),
+                toggle(0, 0, East, 1, 6),
+                toggle(6, 0, West, 2, 3),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_levels_solutions_win() {
+        use FaceDirection::*;
+        assert_wins(
+            "test_pillars",
+            &[
+                toggle(0, 0, East, 2, 4),
+                toggle(0, 0, East, 3, 5),
+                toggle(0, 1, West, 3, 5),
+                toggle(6, 1, East, 2, 4),
+                toggle(60, 1, East, 3, 5),
+            ],
+        );
+        assert_wins("test_bridge", &[toggle(0, 1, East, 2, 4)]);
+        assert_wins("test_corner", &[toggle(0, 0, South, 0, 2)]);
+    }
+
+    #[test]
+    fn rod_cannot_be_toggled_while_moving() {
+        let mut game = HeadlessGame::new("I").unwrap();
+        let toggle = toggle(0, 0, FaceDirection::East, 2, 4).action;
+        assert!(game.apply(&toggle));
+        // Sticking out of the West face now
+        let toggle_back = Action::ToggleRod {
+            pillar: 0,
+            face: FaceDirection::West,
+            position: TilePosition { i: 2, j: 4 },
+        };
+        assert!(!game.apply(&toggle_back));
+        game.advance_ticks(60);
+        assert!(game.apply(&toggle_back));
+    }
+
+    #[test]
+    fn unsolved_level_is_not_won() {
+        let mut game = HeadlessGame::new("I").unwrap();
+        game.advance_to_tick(MAX_TICK);
+        assert_ne!(game.state(), GameState::Won);
+        assert!(!game.level_completion().is_won());
+    }
+
+    #[test]
+    fn replay_reproduces_the_attempt() {
+        use FaceDirection::*;
+        let mut game = HeadlessGame::new("II").unwrap();
+        let attempt = [
+            toggle(0, 0, West, 3, 5),
+            toggle(6, 0, East, 2, 4),
+            toggle(60, 0, East, 3, 5),
+        ];
+        assert!(game.play(&attempt, 100));
+        let recorded = game.simulation().clone();
+        let replay = Replay::from_history(game.level_id(), game.world().resource::<LevelHistory>());
+        assert_eq!(replay.actions.len(), attempt.len());
+
+        game.start_playback(ReplayPlayback::new(replay));
+        assert_eq!(game.simulation().tick(), 0);
+        game.advance_to_tick(100);
+        assert_eq!(*game.simulation(), recorded);
+    }
+}
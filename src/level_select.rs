@@ -0,0 +1,262 @@
+use std::collections::HashSet;
+
+use bevy::{
+    asset::Assets,
+    ecs::system::SystemParam,
+    prelude::{
+        default, BuildChildren, ButtonBundle, Changed, ChildBuilder, Color, Commands, Component,
+        DespawnRecursiveExt, DetectChanges, Entity, EventReader, EventWriter, Input, KeyCode,
+        Local, NextState, NodeBundle, Query, Ref, Res, ResMut, Resource, State, TextBundle,
+        Visibility, With,
+    },
+    text::TextStyle,
+    ui::{
+        AlignItems, BackgroundColor, FlexDirection, FocusPolicy, Interaction, JustifyContent,
+        PositionType, Size, Style, UiRect, Val, ZIndex,
+    },
+};
+
+use crate::{
+    assets::GameAssets,
+    data::{Campaign, LevelData},
+    logic::level::{GameLevels, LevelEvent, LevelWon},
+    progress::PlayerProgress,
+    GameState,
+};
+
+const LEVEL_SELECT_KEY: KeyCode = KeyCode::L;
+
+pub const BUTTON_COLOR: Color = Color::rgba(0.15, 0.15, 0.15, 0.9);
+pub const BUTTON_HOVERED_COLOR: Color = Color::rgba(0.35, 0.35, 0.35, 0.9);
+const LOCKED_TEXT_COLOR: Color = Color::GRAY;
+
+/// Root of the level selection overlay
+#[derive(Component, Clone, Debug)]
+pub struct LevelSelectUI;
+
+#[derive(Component, Clone, Debug)]
+pub struct LevelSelectButton {
+    pub level_id: String,
+}
+
+/// Campaign level ids of the levels won since the game started, unlike the saved [`PlayerProgress`]
+#[derive(Resource, Default)]
+pub struct SessionCompletions(pub HashSet<String>);
+
+pub fn record_session_completion(
+    mut level_won_events: EventReader<LevelWon>,
+    game_levels: Res<GameLevels>,
+    campaigns: Res<Assets<Campaign>>,
+    mut session_completions: ResMut<SessionCompletions>,
+) {
+    if level_won_events.iter().count() == 0 {
+        return;
+    }
+    if let Some(level_id) = game_levels.get_current_level_id(&campaigns) {
+        session_completions.0.insert(level_id.to_string());
+    }
+}
+
+pub fn setup_level_select(mut commands: Commands) {
+    commands.spawn((
+        NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                position_type: PositionType::Absolute,
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+            background_color: Color::rgba(0., 0., 0., 0.6).into(),
+            visibility: Visibility::Hidden,
+            z_index: ZIndex::Global(10),
+            ..default()
+        },
+        // Blocks the picking of the level behind the overlay
+        Interaction::default(),
+        FocusPolicy::Block,
+        LevelSelectUI,
+    ));
+}
+
+fn spawn_level_list(
+    parent: &mut ChildBuilder,
+    assets: &GameAssets,
+    campaign: &Campaign,
+    level_assets: &Assets<LevelData>,
+    progress: &PlayerProgress,
+    session_completions: &SessionCompletions,
+) {
+    let title_style = TextStyle {
+        font: assets.font.clone(),
+        font_size: 45.0,
+        color: Color::WHITE,
+    };
+    let chapter_style = TextStyle {
+        font_size: 30.0,
+        ..title_style.clone()
+    };
+    let level_style = TextStyle {
+        font_size: 25.0,
+        ..title_style.clone()
+    };
+
+    parent.spawn(TextBundle::from_section("Select a level", title_style));
+    let mut chapter = None;
+    for (level_idx, level) in campaign.levels.iter().enumerate() {
+        if chapter != Some(level.chapter) {
+            chapter = Some(level.chapter);
+            parent.spawn(
+                TextBundle::from_section(&campaign.chapters[level.chapter], chapter_style.clone())
+                    .with_style(Style {
+                        margin: UiRect::top(Val::Px(15.0)),
+                        ..default()
+                    }),
+            );
+        }
+
+        let name = level_assets
+            .get(&level.data)
+            .map_or(level.id.as_str(), |level_data| level_data.name.as_str());
+        let unlocked = campaign.is_level_unlocked(level_idx, |id| progress.is_completed(id));
+        let session = if session_completions.0.contains(&level.id) {
+            " - completed this session"
+        } else {
+            ""
+        };
+        // Best results may come from earlier sessions
+        let label = match progress.completed_levels.get(&level.id) {
+            Some(record) => {
+                let seconds = record.best_time / 1000;
+                format!(
+                    "{name}{session} - saved best: {} moves, {}:{:02}",
+                    record.best_moves,
+                    seconds / 60,
+                    seconds % 60
+                )
+            }
+            None if unlocked => format!("{name}{session}"),
+            None => format!("{name} - locked"),
+        };
+
+        let mut button = parent.spawn(ButtonBundle {
+            style: Style {
+                margin: UiRect::all(Val::Px(3.0)),
+                padding: UiRect::new(Val::Px(10.0), Val::Px(10.0), Val::Px(2.0), Val::Px(2.0)),
+                ..default()
+            },
+            background_color: BUTTON_COLOR.into(),
+            ..default()
+        });
+        button.with_children(|button| {
+            button.spawn(TextBundle::from_section(
+                label,
+                TextStyle {
+                    color: if unlocked {
+                        Color::WHITE
+                    } else {
+                        LOCKED_TEXT_COLOR
+                    },
+                    ..level_style.clone()
+                },
+            ));
+        });
+        if unlocked {
+            button.insert(LevelSelectButton {
+                level_id: level.id.clone(),
+            });
+        }
+    }
+}
+
+/// Opening the level selection while playing pauses the game, so that the level does not go on behind it. Closing it
+/// with the same key resumes the game it paused.
+pub fn toggle_level_select(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut level_select_ui: Query<&mut Visibility, With<LevelSelectUI>>,
+    state: Res<State<GameState>>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut paused_game: Local<bool>,
+) {
+    if keyboard_input.just_pressed(LEVEL_SELECT_KEY) {
+        let mut visibility = level_select_ui.single_mut();
+        *visibility = match *visibility {
+            Visibility::Hidden => {
+                *paused_game = state.0 == GameState::Playing;
+                if *paused_game {
+                    next_state.set(GameState::Paused);
+                }
+                Visibility::Visible
+            }
+            _ => {
+                if *paused_game && state.0 == GameState::Paused {
+                    next_state.set(GameState::Playing);
+                }
+                *paused_game = false;
+                Visibility::Hidden
+            }
+        };
+    }
+}
+
+/// Levels and their state, listed by [`populate_level_select`]
+#[derive(SystemParam)]
+pub struct LevelList<'w> {
+    assets: Res<'w, GameAssets>,
+    game_levels: Res<'w, GameLevels>,
+    campaigns: Res<'w, Assets<Campaign>>,
+    level_assets: Res<'w, Assets<LevelData>>,
+    progress: Res<'w, PlayerProgress>,
+    session_completions: Res<'w, SessionCompletions>,
+}
+
+/// Lists the levels with their state at the time the level selection is opened
+pub fn populate_level_select(
+    mut commands: Commands,
+    level_select_ui: Query<(Entity, Ref<Visibility>), With<LevelSelectUI>>,
+    level_list: LevelList,
+) {
+    let Ok((entity, visibility)) = level_select_ui.get_single() else {
+        return;
+    };
+    if !visibility.is_changed() {
+        return;
+    }
+    commands.entity(entity).despawn_descendants();
+    if *visibility == Visibility::Hidden {
+        return;
+    }
+    if let Some(campaign) = level_list.campaigns.get(level_list.game_levels.campaign()) {
+        commands.entity(entity).with_children(|parent| {
+            spawn_level_list(
+                parent,
+                &level_list.assets,
+                campaign,
+                &level_list.level_assets,
+                &level_list.progress,
+                &level_list.session_completions,
+            );
+        });
+    }
+}
+
+pub fn handle_level_select_buttons(
+    mut buttons: Query<
+        (&Interaction, &mut BackgroundColor, &LevelSelectButton),
+        Changed<Interaction>,
+    >,
+    mut level_select_ui: Query<&mut Visibility, With<LevelSelectUI>>,
+    mut level_events: EventWriter<LevelEvent>,
+) {
+    for (interaction, mut background_color, level_button) in buttons.iter_mut() {
+        match interaction {
+            Interaction::Clicked => {
+                level_events.send(LevelEvent::Load(level_button.level_id.clone()));
+                *level_select_ui.single_mut() = Visibility::Hidden;
+            }
+            Interaction::Hovered => *background_color = BUTTON_HOVERED_COLOR.into(),
+            Interaction::None => *background_color = BUTTON_COLOR.into(),
+        }
+    }
+}
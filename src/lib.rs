@@ -0,0 +1,256 @@
+use std::f32::consts::PI;
+
+use assets::GameAssets;
+use audio::{
+    apply_music_volume, play_climber_sounds, play_level_music, play_rod_sounds, play_win_sound,
+    select_level_music, LevelMusic,
+};
+use bevy::prelude::{
+    default, not, resource_exists, shape, App, AssetServer, Assets, Color, Commands, Handle,
+    IntoSystemAppConfig, IntoSystemConfig, Mesh, Name, OnEnter, PbrBundle, Plugin, Quat, Res,
+    ResMut, Resource, StandardMaterial, States, Transform, Vec3,
+};
+
+use bevy_mod_picking::{DefaultHighlighting, DefaultPickingPlugins};
+use bevy_tweening::TweeningPlugin;
+use camera::{setup_camera, CustomOrbitCameraPlugin};
+use data::Campaign;
+use logic::{
+    climber::ClimberPlugin,
+    level::{
+        apply_shadow_settings, climber_event_handler, spawn_pending_level, GameLevels, LevelPlugin,
+        LevelSimulation,
+    },
+    log_gameplay_events,
+    rod::RodPlugin,
+};
+use progress::{is_playing_campaign, save_last_played_level, save_level_completion, ProgressStore};
+use replay::{save_replay, ReplayPlayback, ReplayStore};
+use settings::{handle_settings_buttons, save_settings, SettingsStore};
+use smooth_bevy_cameras::LookTransformPlugin;
+use ui::GameUiPlugin;
+
+#[cfg(feature = "grass")]
+use grass::{apply_grass_settings, setup_grass};
+#[cfg(feature = "grass")]
+use warbler_grass::warblers_plugin::WarblersPlugin;
+
+#[cfg(feature = "dev-tools")]
+use bevy::{
+    diagnostic::FrameTimeDiagnosticsPlugin,
+    input::common_conditions::input_toggle_active,
+    prelude::{EventWriter, Input, KeyCode},
+};
+#[cfg(feature = "dev-tools")]
+use bevy_inspector_egui::quick::WorldInspectorPlugin;
+#[cfg(feature = "dev-tools")]
+use debug::{display_stats_ui, EguiInputBlockerPlugin};
+#[cfg(feature = "dev-tools")]
+use logic::level::LevelEvent;
+
+pub mod assets;
+pub mod audio;
+pub mod camera;
+pub mod data;
+pub mod generator;
+#[cfg(feature = "grass")]
+pub mod grass;
+#[cfg(any(test, feature = "headless"))]
+pub mod headless;
+pub mod level_select;
+pub mod logic;
+pub mod menu;
+pub mod progress;
+pub mod replay;
+pub mod settings;
+pub mod simulation;
+pub mod solver;
+pub mod storage;
+pub mod tools;
+pub mod ui;
+
+#[cfg(feature = "dev-tools")]
+pub mod debug;
+
+#[derive(Resource, Default)]
+pub struct EguiBlockInputState {
+    pub wants_keyboard_input: bool,
+    pub wants_pointer_input: bool,
+}
+
+#[derive(Default, Clone, Eq, PartialEq, Debug, Hash, States)]
+pub enum GameState {
+    #[default]
+    MainMenu,
+    /// The current level is frozen behind the pause menu
+    Paused,
+    Playing,
+    Lost,
+    Won,
+    CampaignCompleted,
+}
+
+// THEMES
+
+// Pillar material 193, 109, 0, 255
+// Front dir light WHITE
+
+// Autumn
+// Ambient 255, 68, 0, 255  Brightness 0.2
+// Clear color 109, 241, 255, 255
+// Grass MC 149, 45, 0, 255
+// Grass BC 34, 6, 6, 255
+// Ground 79, 30, 0, 255
+
+// Fushia
+
+pub const CAMERA_CLEAR_COLOR: Color = Color::rgb(0.56, 1., 0.98); // 144, 255, 253, 255
+
+pub const WINDOW_TITLE: &str = "Bevy-jam-3";
+
+pub const CAMPAIGN: &str = "levels/main.campaign.ron";
+
+#[cfg(feature = "dev-tools")]
+fn skip_level(mut level_events: EventWriter<LevelEvent>, keyboard_input: Res<Input<KeyCode>>) {
+    if keyboard_input.just_pressed(KeyCode::N) {
+        level_events.send(LevelEvent::LoadNext);
+    }
+}
+
+fn setup_scene(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    assets: Res<GameAssets>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    // sky
+    // commands.spawn(PbrBundle {
+    //     mesh: meshes.add(Mesh::from(shape::Box::default())),
+    //     material: materials.add(StandardMaterial {
+    //         base_color: Color::hex("888888").unwrap(),
+    //         unlit: true,
+    //         cull_mode: None,
+    //         ..default()
+    //     }),
+    //     transform: Transform::from_scale(Vec3::splat(1_000_000.0)),
+    //     ..default()
+    // });
+
+    // Ground TODO : move to level specific
+    commands.spawn((
+        PbrBundle {
+            mesh: meshes.add(Mesh::from(shape::Circle::new(20.))),
+            material: materials.add(StandardMaterial {
+                base_color: Color::rgb(0.23, 0., 0.29), // 59, 0, 75, 255
+                metallic: 0.,
+                reflectance: 0.,
+                // unlit: true,
+                // cull_mode: None,
+                ..default()
+            }),
+            transform: Transform::from_rotation(Quat::from_axis_angle(Vec3::X, -1. * PI / 2.)),
+            ..default()
+        },
+        Name::new("Ground"),
+    ));
+
+    commands.insert_resource(DefaultHighlighting {
+        hovered: assets.movable_rod_highlight_mat.clone(),
+        pressed: assets.movable_rod_mat.clone(),
+        selected: assets.climber_mat.clone(),
+    });
+}
+
+/// The whole game, on top of Bevy's `DefaultPlugins`
+#[derive(Default)]
+pub struct PillarClimbPlugin {
+    /// Campaign level id, or level number counting from 1, to start from instead of the last played level
+    pub start_level: Option<String>,
+    /// World inspector (F2), FPS counter (F3) and level skipping (N). Requires the `dev-tools` feature.
+    pub dev_tools: bool,
+}
+
+impl Plugin for PillarClimbPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugin(TweeningPlugin)
+            .add_plugin(LookTransformPlugin)
+            .add_plugin(CustomOrbitCameraPlugin)
+            .add_plugins(DefaultPickingPlugins)
+            .add_plugin(LevelPlugin)
+            .add_plugin(ClimberPlugin)
+            .add_plugin(RodPlugin)
+            .add_plugin(GameUiPlugin);
+
+        let campaign: Handle<Campaign> = app.world.resource::<AssetServer>().load(CAMPAIGN);
+        let settings_store = SettingsStore::for_platform();
+        let progress_store = ProgressStore::for_platform();
+        let progress = progress_store.load_or_default();
+        let start_level = self
+            .start_level
+            .clone()
+            .or_else(|| progress.last_played_level.clone());
+        app.init_resource::<GameAssets>()
+            .init_resource::<LevelMusic>()
+            .insert_resource(GameLevels::new(campaign, start_level))
+            .insert_resource(progress)
+            .insert_resource(progress_store)
+            .insert_resource(ReplayStore::for_platform())
+            .insert_resource(settings_store.load_or_default())
+            .insert_resource(settings_store);
+
+        app.add_startup_system(setup_camera)
+            .add_startup_system(setup_scene);
+
+        app.add_system(save_settings.after(handle_settings_buttons))
+            .add_system(log_gameplay_events)
+            .add_system(play_rod_sounds)
+            .add_system(play_climber_sounds)
+            .add_system(play_win_sound.after(climber_event_handler))
+            .add_system(select_level_music.after(spawn_pending_level))
+            .add_system(play_level_music.after(select_level_music))
+            .add_system(apply_music_volume)
+            .add_system(apply_shadow_settings)
+            .add_system(
+                save_last_played_level
+                    .after(spawn_pending_level)
+                    .run_if(is_playing_campaign),
+            );
+        app.add_system(
+            save_level_completion
+                .in_schedule(OnEnter(GameState::Won))
+                .run_if(resource_exists::<LevelSimulation>())
+                .run_if(is_playing_campaign),
+        )
+        .add_system(
+            save_replay
+                .in_schedule(OnEnter(GameState::Won))
+                .run_if(not(resource_exists::<ReplayPlayback>()))
+                .run_if(is_playing_campaign),
+        )
+        .add_system(
+            save_replay
+                .in_schedule(OnEnter(GameState::Lost))
+                .run_if(not(resource_exists::<ReplayPlayback>()))
+                .run_if(is_playing_campaign),
+        );
+
+        // Without it the ground is left plain
+        #[cfg(feature = "grass")]
+        {
+            app.add_plugin(WarblersPlugin)
+                .add_startup_system(setup_grass)
+                .add_system(apply_grass_settings);
+        }
+
+        #[cfg(feature = "dev-tools")]
+        if self.dev_tools {
+            app.add_plugin(
+                WorldInspectorPlugin::new().run_if(input_toggle_active(true, KeyCode::F2)),
+            )
+            .add_plugin(EguiInputBlockerPlugin)
+            .add_plugin(FrameTimeDiagnosticsPlugin::default())
+            .add_system(display_stats_ui.run_if(input_toggle_active(true, KeyCode::F3)))
+            .add_system(skip_level);
+        }
+    }
+}
@@ -1,35 +1,31 @@
-use bevy::prelude::{Component, EventReader, EventWriter, Query, Res, With};
+use bevy::{
+    ecs::system::SystemParam,
+    prelude::{debug, Component, EventReader, EventWriter, Query, Res, Vec3, With},
+};
 use bevy_mod_picking::{PickingEvent, SelectionEvent};
 
-use self::level::{LevelCompletion, LevelEvent};
+use crate::simulation::PylonId;
+
+use self::{
+    climber::{ClimberDied, ClimberLanded, ClimberMoved, ClimberStartedFalling, PylonPowered},
+    level::{LevelCompletion, LevelEvent, LevelLoaded, LevelLost, LevelWon},
+    rod::RodToggled,
+};
 
 pub mod climber;
 pub mod face;
+pub mod hint;
+pub mod history;
 pub mod level;
 pub mod pillar;
 pub mod rod;
 
-#[derive(Clone, Copy, Debug)]
-pub struct TilePosition {
-    pub i: u16,
-    pub j: u16,
-}
-
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
-pub enum TileType {
-    Void,
-    StaticRod(bool),
-    MovableRod(bool),
-}
-
-// #[derive(Clone, Debug)]
-// pub struct TileData {
-//     pub kind: TileType,
-// }
-
 #[derive(Component, Clone, Debug)]
 pub struct Pylon {
+    pub id: PylonId,
     pub powered: bool,
+    /// Translation while unpowered, powered pylons are raised
+    pub rest_translation: Vec3,
 }
 
 #[derive(Component, Clone, Debug)]
@@ -56,3 +52,52 @@ pub fn handle_win_pylon_pick_events(
         }
     }
 }
+
+#[derive(SystemParam)]
+pub struct GameplayEventReaders<'w, 's> {
+    rod_toggled: EventReader<'w, 's, RodToggled>,
+    climber_moved: EventReader<'w, 's, ClimberMoved>,
+    climber_started_falling: EventReader<'w, 's, ClimberStartedFalling>,
+    climber_landed: EventReader<'w, 's, ClimberLanded>,
+    climber_died: EventReader<'w, 's, ClimberDied>,
+    pylon_powered: EventReader<'w, 's, PylonPowered>,
+    level_won: EventReader<'w, 's, LevelWon>,
+    level_lost: EventReader<'w, 's, LevelLost>,
+    level_loaded: EventReader<'w, 's, LevelLoaded>,
+}
+
+/// Traces the course of a level attempt, at the debug log level
+pub fn log_gameplay_events(mut events: GameplayEventReaders) {
+    for LevelLoaded { level, level_id } in events.level_loaded.iter() {
+        debug!("Level {level_id} loaded as {level:?}");
+    }
+    for RodToggled {
+        rod,
+        from_face,
+        to_face,
+    } in events.rod_toggled.iter()
+    {
+        debug!("Rod {rod:?} toggled from {from_face:?} to {to_face:?}");
+    }
+    for ClimberMoved { climber, from, to } in events.climber_moved.iter() {
+        debug!("Climber {climber:?} moving from {from:?} to {to:?}");
+    }
+    for ClimberStartedFalling { climber, from } in events.climber_started_falling.iter() {
+        debug!("Climber {climber:?} falling from {from:?}");
+    }
+    for ClimberLanded { climber, on } in events.climber_landed.iter() {
+        debug!("Climber {climber:?} landed on {on:?}");
+    }
+    for ClimberDied { climber } in events.climber_died.iter() {
+        debug!("Climber {climber:?} died");
+    }
+    for PylonPowered { pylon, climber } in events.pylon_powered.iter() {
+        debug!("Climber {climber:?} powered pylon {pylon:?}");
+    }
+    if events.level_won.iter().count() > 0 {
+        debug!("Level won");
+    }
+    if events.level_lost.iter().count() > 0 {
+        debug!("Level lost");
+    }
+}
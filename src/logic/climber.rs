@@ -1,8 +1,12 @@
 use std::time::Duration;
 
-use bevy::prelude::{
-    default, BuildChildren, Commands, Component, Entity, EventWriter, Handle, Name, NextState,
-    PbrBundle, Query, Res, ResMut, StandardMaterial, Transform, Vec3, Without,
+use bevy::{
+    ecs::system::SystemParam,
+    prelude::{
+        default, in_state, resource_exists, App, BuildChildren, Commands, Component, CoreSchedule,
+        Entity, EventWriter, Handle, IntoSystemAppConfig, IntoSystemConfig, Name, NextState,
+        PbrBundle, Plugin, Query, Res, ResMut, StandardMaterial, Transform, Vec3, Without,
+    },
 };
 use bevy_tweening::{
     lens::{TransformPositionLens, TransformScaleLens},
@@ -14,54 +18,81 @@ use crate::{
         GameAssets, CLIMBER_LEVITATE_DISTANCE, CLIMBER_RADIUS, PYLON_ANIMATION_DURATION,
         PYLON_HEIGHT, PYLON_VERTICAL_MOVEMENT_AMPLITUDE,
     },
-    data::ClimberData,
-    Face, GameState, Pillar, Pylon,
+    simulation::{ClimberState, FaceId, FaceTile, SimulationEvent, SimulationStatus},
+    GameState,
+};
+
+use super::{
+    face::Face,
+    level::{climber_event_handler, LevelCompletion, LevelLost, LevelSimulation},
+    Pylon,
 };
 
+/// A climber started moving to the next tile of its path
 #[derive(Clone, Debug)]
-pub struct ClimberPosition {
-    pub face: Entity,
-    pub i: u16,
-    pub j: u16,
+pub struct ClimberMoved {
+    pub climber: Entity,
+    pub from: FaceTile,
+    pub to: FaceTile,
 }
 
+/// The rod a climber was waiting on is not there anymore
 #[derive(Clone, Debug)]
-pub enum ClimberEvent {
-    ReachedTop,
+pub struct ClimberStartedFalling {
+    pub climber: Entity,
+    pub from: FaceTile,
 }
 
+/// A falling climber was caught by a rod
 #[derive(Clone, Debug)]
-enum ClimberState {
-    Waiting {
-        on_tile: ClimberPosition,
-        // next_tile: ClimberPosition,
-        // direction: ClimberDirection,
-    },
-    Moving {
-        to_tile: ClimberPosition,
-        // direction: ClimberDirection,
-    },
-    Falling {
-        on_face: Entity,
-    },
-    Saved,
-    Dead,
+pub struct ClimberLanded {
+    pub climber: Entity,
+    pub on: FaceTile,
+}
+
+/// A climber fell too far, the level is lost
+#[derive(Clone, Debug)]
+pub struct ClimberDied {
+    pub climber: Entity,
 }
 
+/// A climber reached the top of its pillar and powered its pylon
+#[derive(Clone, Debug)]
+pub struct PylonPowered {
+    pub pylon: Entity,
+    pub climber: Entity,
+}
+
+/// Events sent by [`update_climbers`] when mirroring the simulation
+#[derive(SystemParam)]
+pub struct ClimberEventWriters<'w> {
+    moved: EventWriter<'w, ClimberMoved>,
+    started_falling: EventWriter<'w, ClimberStartedFalling>,
+    landed: EventWriter<'w, ClimberLanded>,
+    died: EventWriter<'w, ClimberDied>,
+    pylon_powered: EventWriter<'w, PylonPowered>,
+    level_lost: EventWriter<'w, LevelLost>,
+}
+
+/// Visual representation of a [`crate::simulation::Climber`]
 #[derive(Component, Clone, Debug)]
 pub struct Climber {
-    state: ClimberState,
-    current_pillar: Entity,
+    /// Index in [`crate::simulation::Simulation::climbers`]
+    pub index: usize,
 }
 
+/// Relative to the pylon the climber is attached to
+pub const CLIMBER_TRANSLATION_ON_PYLON: Vec3 = Vec3::new(
+    0.,
+    PYLON_HEIGHT / 2. + CLIMBER_RADIUS + CLIMBER_LEVITATE_DISTANCE,
+    0.,
+);
+
 fn climber_start_moving(
     translation: &Vec3,
     next_translation: &Vec3,
-    // tile: &ClimberPosition,
-    next_tile: &ClimberPosition,
-    // direction: ClimberDirection,
     animator: &mut Animator<Transform>,
-) -> ClimberState {
+) {
     let tween = Tween::new(
         EaseFunction::QuadraticInOut,
         Duration::from_millis(400),
@@ -74,8 +105,8 @@ fn climber_start_moving(
         EaseFunction::QuadraticInOut,
         Duration::from_millis(1),
         TransformPositionLens {
-            start: translation.clone(),
-            end: next_translation.clone(),
+            start: *translation,
+            end: *next_translation,
         },
     ))
     .then(
@@ -89,151 +120,127 @@ fn climber_start_moving(
         ), // .with_completed_event(),
     );
     animator.set_tweenable(tween);
+}
 
-    ClimberState::Moving {
-        to_tile: next_tile.clone(),
-        // direction,
-    }
+pub fn get_face<'a>(faces: &'a Query<&Face>, id: &FaceId) -> &'a Face {
+    faces
+        .iter()
+        .find(|face| face.id() == *id)
+        .expect("Simulation face does not appear to have a Face entity")
 }
 
+/// Advances the level simulation by one tick and mirrors its changes on the climbers and pylons
 pub fn update_climbers(
     mut commands: Commands,
-    mut climbers: Query<(
-        &mut Transform,
-        &mut Climber,
-        &mut Animator<Transform>,
-        Entity,
-    )>,
-    mut faces: Query<&mut Face>,
-    mut pillars: Query<&mut Pillar>,
+    mut simulation: ResMut<LevelSimulation>,
+    mut climbers: Query<(&mut Transform, &Climber, &mut Animator<Transform>, Entity)>,
+    faces: Query<&Face>,
     mut pylons: Query<
-        (&mut Pylon, &mut Transform, &mut Handle<StandardMaterial>),
+        (
+            &mut Pylon,
+            &mut Transform,
+            &mut Handle<StandardMaterial>,
+            Entity,
+        ),
         Without<Climber>,
     >,
     assets: Res<GameAssets>,
     mut next_state: ResMut<NextState<GameState>>,
-    mut climber_events: EventWriter<ClimberEvent>,
+    mut climber_events: ClimberEventWriters,
 ) {
-    for (mut transform, mut climber, mut animator, climber_entity) in climbers.iter_mut() {
-        match &climber.state {
-            ClimberState::Waiting { on_tile: tile } => {
-                let mut face = faces
-                    .get_mut(tile.face)
-                    .expect("Climber does not appear to have a Face reference");
-                // If climber doesn't have a rod beneath him anymore : falling
-                if !face.has_ground_on_tile(tile.i, tile.j) {
-                    climber.state = ClimberState::Falling { on_face: tile.face };
-                } else {
-                    if let Some(next_tile) = face.get_next_free_tile_with_ground(tile) {
-                        let next_pos = face.climber_get_pos_from_tile(&next_tile);
-                        face.set_free(tile);
-                        face.set_occupied(&next_tile);
-                        climber.state = climber_start_moving(
-                            &transform.translation,
-                            &next_pos,
-                            &next_tile,
-                            // *direction,
-                            &mut animator,
-                        );
-                    }
-                }
-            }
-            ClimberState::Moving { to_tile: to } => {
-                if animator.tweenable().progress() >= 1. {
-                    // TODO No tweening for pillars & climbers, animate according to fixed updates.
-                    let mut face = faces
-                        .get_mut(to.face)
-                        .expect("Climber does not appear to have a Face reference");
-
-                    if to.j >= face.size.h - 1 {
-                        face.set_free(to);
-                        // TODO Move that code
-                        let mut pillar = pillars.get_mut(climber.current_pillar).unwrap();
-                        let pylon_entity = if let Some(same_face_pylon) =
-                            pillar.get_pylon_from_face(&face.direction)
-                        {
-                            same_face_pylon
-                        } else {
-                            pillar.pop_first_available_pylon().unwrap()
-                        };
+    let was_lost = simulation.0.status() == SimulationStatus::Lost;
+    let events = simulation.0.step();
 
-                        let (mut pylon, pylon_transform, mut mat_handle) =
-                            pylons.get_mut(pylon_entity).unwrap();
-                        pylon.powered = true;
-                        *mat_handle = assets.climber_mat.clone();
-
-                        let pos = pylon_transform.translation;
-                        let tween = Tween::new(
-                            EaseFunction::QuadraticInOut,
-                            Duration::from_millis(PYLON_ANIMATION_DURATION),
-                            TransformPositionLens {
-                                start: pos,
-                                end: Vec3::new(
-                                    pos.x,
-                                    pos.y + PYLON_VERTICAL_MOVEMENT_AMPLITUDE,
-                                    pos.z,
-                                ),
-                            },
-                        );
-                        commands.entity(pylon_entity).insert(Animator::new(tween));
-                        transform.translation = Vec3::new(
-                            0.,
-                            PYLON_HEIGHT / 2. + CLIMBER_RADIUS + CLIMBER_LEVITATE_DISTANCE,
-                            0.,
-                        );
-                        commands.entity(pylon_entity).add_child(climber_entity);
-                        climber.state = ClimberState::Saved;
-                        climber_events.send(ClimberEvent::ReachedTop);
-                    } else {
-                        climber.state = ClimberState::Waiting {
-                            on_tile: to.clone(),
-                        };
-                    }
+    for (mut transform, climber, mut animator, climber_entity) in climbers.iter_mut() {
+        for event in events.iter() {
+            match event {
+                SimulationEvent::ClimberMoved {
+                    climber: idx,
+                    from,
+                    to,
+                } if *idx == climber.index => {
+                    let next_pos =
+                        get_face(&faces, &to.face).climber_get_pos_from_tile(to.position);
+                    climber_start_moving(&transform.translation, &next_pos, &mut animator);
+                    climber_events.moved.send(ClimberMoved {
+                        climber: climber_entity,
+                        from: from.clone(),
+                        to: to.clone(),
+                    });
                 }
-            }
-            ClimberState::Falling {
-                on_face: face_entity,
-            } => {
-                // If a rod is reached : waiting
-                let mut face = faces
-                    .get_mut(*face_entity)
-                    .expect("Climber does not appear to have a Face reference");
-
-                let (i, j) = face.get_tile_coords_from_pos(transform.translation);
-                if face.has_ground_on_tile(i, j) {
-                    let landed_on = ClimberPosition {
-                        face: *face_entity,
-                        i,
-                        j,
-                    };
-                    face.set_occupied(&landed_on);
-                    transform.translation = face.climber_get_pos_from_tile(&landed_on);
-                    climber.state = ClimberState::Waiting { on_tile: landed_on };
-                } else {
-                    transform.translation.y -= 0.05;
+                SimulationEvent::ClimberStartedFalling { climber: idx, from }
+                    if *idx == climber.index =>
+                {
+                    climber_events.started_falling.send(ClimberStartedFalling {
+                        climber: climber_entity,
+                        from: from.clone(),
+                    });
+                }
+                SimulationEvent::ClimberLanded { climber: idx, on } if *idx == climber.index => {
+                    transform.translation =
+                        get_face(&faces, &on.face).climber_get_pos_from_tile(on.position);
+                    climber_events.landed.send(ClimberLanded {
+                        climber: climber_entity,
+                        on: on.clone(),
+                    });
                 }
+                SimulationEvent::ClimberDied { climber: idx } if *idx == climber.index => {
+                    climber_events.died.send(ClimberDied {
+                        climber: climber_entity,
+                    });
+                }
+                SimulationEvent::ClimberSaved {
+                    climber: idx,
+                    pylon,
+                } if *idx == climber.index => {
+                    let (mut pylon, pylon_transform, mut mat_handle, pylon_entity) = pylons
+                        .iter_mut()
+                        .find(|(p, ..)| p.id == *pylon)
+                        .expect("Simulation pylon does not appear to have a Pylon entity");
+                    pylon.powered = true;
+                    *mat_handle = assets.climber_mat.clone();
 
-                // If the ground is reached : dead
-                if transform.translation.y <= 0.0 {
-                    climber.state = ClimberState::Dead;
-                    next_state.set(GameState::Lost);
+                    let pos = pylon_transform.translation;
+                    let tween = Tween::new(
+                        EaseFunction::QuadraticInOut,
+                        Duration::from_millis(PYLON_ANIMATION_DURATION),
+                        TransformPositionLens {
+                            start: pos,
+                            end: Vec3::new(pos.x, pos.y + PYLON_VERTICAL_MOVEMENT_AMPLITUDE, pos.z),
+                        },
+                    );
+                    commands.entity(pylon_entity).insert(Animator::new(tween));
+                    transform.translation = CLIMBER_TRANSLATION_ON_PYLON;
+                    commands.entity(pylon_entity).add_child(climber_entity);
+                    climber_events.pylon_powered.send(PylonPowered {
+                        pylon: pylon_entity,
+                        climber: climber_entity,
+                    });
                 }
+                _ => (),
             }
-            ClimberState::Saved => {}
-            ClimberState::Dead => {}
         }
+
+        if let ClimberState::Falling { face, i, height } =
+            &simulation.0.climbers()[climber.index].state
+        {
+            transform.translation =
+                get_face(&faces, face).climber_get_pos_while_falling(*i, *height);
+        }
+    }
+
+    // Several ticks can run before the state transition is applied
+    if !was_lost && simulation.0.status() == SimulationStatus::Lost {
+        next_state.set(GameState::Lost);
+        climber_events.level_lost.send(LevelLost);
     }
 }
 
 pub fn spawn_climber(
     commands: &mut Commands,
     assets: &Res<GameAssets>,
-    face_entity: Entity,
-    pillar_entity: Entity,
-    climber_data: &ClimberData,
-    x: f32,
-    y: f32,
-    z: f32,
+    climber_index: usize,
+    translation: Vec3,
 ) -> Entity {
     let tween = Tween::new(
         EaseFunction::QuadraticInOut,
@@ -250,20 +257,36 @@ pub fn spawn_climber(
         .spawn((PbrBundle {
             mesh: assets.climber_mesh.clone(),
             material: assets.climber_mat.clone(),
-            transform: Transform::from_xyz(x, y, z),
+            transform: Transform::from_translation(translation),
             ..default()
         },))
         .insert(Climber {
-            state: ClimberState::Waiting {
-                on_tile: ClimberPosition {
-                    face: face_entity,
-                    i: climber_data.tile_i,
-                    j: climber_data.tile_j,
-                },
-            },
-            current_pillar: pillar_entity,
+            index: climber_index,
         })
         .insert(Animator::new(tween))
         .insert(Name::from("Climber"))
         .id()
 }
+
+/// Steps the level simulation at a fixed rate, moving the climbers and powering the pylons. Requires [`LevelPlugin`].
+///
+/// [`LevelPlugin`]: super::level::LevelPlugin
+pub struct ClimberPlugin;
+
+impl Plugin for ClimberPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ClimberMoved>()
+            .add_event::<ClimberStartedFalling>()
+            .add_event::<ClimberLanded>()
+            .add_event::<ClimberDied>()
+            .add_event::<PylonPowered>();
+
+        app.add_system(
+            update_climbers
+                .in_schedule(CoreSchedule::FixedUpdate)
+                .run_if(in_state(GameState::Playing))
+                .run_if(resource_exists::<LevelSimulation>()),
+        )
+        .add_system(climber_event_handler.run_if(resource_exists::<LevelCompletion>()));
+    }
+}
@@ -6,130 +6,60 @@ use crate::{
         TILE_SIZE,
     },
     data::{FaceDirection, FaceSize},
-    TilePosition, TileType,
+    simulation::{FaceId, TilePosition, FALL_STEPS_PER_TILE},
 };
 
-use super::climber::ClimberPosition;
-
+/// Geometry of a pillar face. Its tiles are owned by the level [`crate::simulation::Simulation`].
 #[derive(Component, Clone, Debug)]
 pub struct Face {
+    pub pillar: usize,
     pub direction: FaceDirection,
     pub size: FaceSize,
     pub origin: Vec3,
-    pub tiles: Vec<Vec<TileType>>,
 }
 
 impl Face {
-    pub fn is_valid(&self, i: u16, j: u16) -> bool {
-        i < self.size.w && j < self.size.h
-    }
-
-    pub fn has_ground_on_tile(&self, i: u16, j: u16) -> bool {
-        if !self.is_valid(i, j) {
-            return false;
+    pub fn id(&self) -> FaceId {
+        FaceId {
+            pillar: self.pillar,
+            direction: self.direction.clone(),
         }
-        self.tiles[i as usize][j as usize] != TileType::Void
     }
 
-    pub fn has_free_ground_on_tile(&self, i: u16, j: u16) -> bool {
-        if !self.is_valid(i, j) {
-            return false;
-        }
-        // self.tiles[i as usize][j as usize] != TileType::Void;
-
-        match self.tiles[i as usize][j as usize] {
-            TileType::Void => false,
-            TileType::StaticRod(occupied) => !occupied,
-            TileType::MovableRod(occupied) => !occupied,
-        }
+    pub fn climber_get_pos_from_tile(&self, pos: TilePosition) -> Vec3 {
+        self.climber_get_pos_while_falling(pos.i, pos.j as i32 * FALL_STEPS_PER_TILE)
     }
 
-    pub fn climber_get_pos_from_tile(&self, pos: &ClimberPosition) -> Vec3 {
-        let factor = match self.direction {
-            FaceDirection::West | FaceDirection::South => -1.,
-            FaceDirection::North | FaceDirection::East => 1.,
-        };
-        let y = self.origin.y
-            + pos.j as f32 * TILE_SIZE
+    /// `height` is in fall steps, see [`crate::simulation::ClimberState::Falling`]
+    pub fn climber_get_pos_while_falling(&self, i: u16, height: i32) -> Vec3 {
+        let y = height as f32 * TILE_SIZE / FALL_STEPS_PER_TILE as f32
             + TILE_SIZE
             + CLIMBER_RADIUS
             + CLIMBER_LEVITATE_DISTANCE;
-        let horizontal_delta = pos.i as f32 * TILE_SIZE + HALF_TILE_SIZE;
-        match self.direction {
-            FaceDirection::West | FaceDirection::East => Vec3::new(
-                self.origin.x + factor * HALF_VISIBLE_ROD_LENGTH,
+        self.origin
+            + get_face_offset(
+                &self.direction,
+                HALF_VISIBLE_ROD_LENGTH,
+                i as f32 * TILE_SIZE + HALF_TILE_SIZE,
                 y,
-                self.origin.z + horizontal_delta,
-            ),
-            FaceDirection::North | FaceDirection::South => Vec3::new(
-                self.origin.x + horizontal_delta,
-                y,
-                self.origin.z + factor * HALF_VISIBLE_ROD_LENGTH,
-            ),
-        }
-    }
-
-    pub fn get_next_free_tile_with_ground(
-        &self,
-        tile: &ClimberPosition,
-        // direction: &ClimberDirection,
-    ) -> Option<ClimberPosition> {
-        let next_tile_1 = ClimberPosition {
-            face: tile.face,
-            i: tile.i + 1,
-            j: tile.j + 1,
-        };
-        if self.has_free_ground_on_tile(next_tile_1.i, next_tile_1.j) {
-            return Some(next_tile_1);
-        }
-        if tile.i > 0 {
-            let next_tile_2 = ClimberPosition {
-                face: tile.face,
-                i: tile.i - 1,
-                j: tile.j + 1,
-            };
-            if self.has_free_ground_on_tile(next_tile_2.i, next_tile_2.j) {
-                return Some(next_tile_2);
-            }
-        }
-
-        None
-    }
-
-    // No input checks
-    pub fn remove_tile_at(&mut self, pos: TilePosition) {
-        self.tiles[pos.i as usize][pos.j as usize] = TileType::Void;
-    }
-
-    pub fn set_tile_at(&mut self, pos: TilePosition, tile_type: TileType) {
-        self.tiles[pos.i as usize][pos.j as usize] = tile_type;
-    }
-
-    pub fn get_tile_coords_from_pos(&self, translation: Vec3) -> (u16, u16) {
-        let relative = translation - self.origin;
-        let j = (relative.y / TILE_SIZE).round() as u16;
-        let i = match self.direction {
-            FaceDirection::West | FaceDirection::East => (relative.z / TILE_SIZE).trunc(),
-            FaceDirection::North | FaceDirection::South => (relative.x / TILE_SIZE).trunc(),
-        } as u16;
-        (i, j)
+            )
     }
+}
 
-    pub(crate) fn set_free(&mut self, tile: &ClimberPosition) {
-        self.tiles[tile.i as usize][tile.j as usize] =
-            match self.tiles[tile.i as usize][tile.j as usize] {
-                TileType::Void => TileType::Void,
-                TileType::StaticRod(_) => TileType::StaticRod(false),
-                TileType::MovableRod(_) => TileType::MovableRod(false),
-            };
+/// `1.` for the faces looking towards the positive x (East) or z (North) axis, `-1.` otherwise
+pub fn get_face_factor(direction: &FaceDirection) -> f32 {
+    match direction {
+        FaceDirection::West | FaceDirection::South => -1.,
+        FaceDirection::North | FaceDirection::East => 1.,
     }
+}
 
-    pub(crate) fn set_occupied(&mut self, tile: &ClimberPosition) {
-        self.tiles[tile.i as usize][tile.j as usize] =
-            match self.tiles[tile.i as usize][tile.j as usize] {
-                TileType::Void => TileType::Void,
-                TileType::StaticRod(_) => TileType::StaticRod(true),
-                TileType::MovableRod(_) => TileType::MovableRod(true),
-            };
+/// Offset of a point located `outward` in front of the `direction` face and `along` its horizontal axis, which is z
+/// for West/East faces and x for North/South faces. Tile columns go towards the positive side of that axis.
+pub fn get_face_offset(direction: &FaceDirection, outward: f32, along: f32, y: f32) -> Vec3 {
+    let outward = get_face_factor(direction) * outward;
+    match direction {
+        FaceDirection::West | FaceDirection::East => Vec3::new(outward, y, along),
+        FaceDirection::North | FaceDirection::South => Vec3::new(along, y, outward),
     }
 }
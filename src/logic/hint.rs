@@ -0,0 +1,212 @@
+use bevy::{
+    prelude::{
+        info, Changed, Commands, Component, DetectChangesMut, Entity, EventReader, Handle, Input,
+        KeyCode, Query, Res, ResMut, Resource, StandardMaterial, With,
+    },
+    tasks::{AsyncComputeTaskPool, Task},
+    ui::Interaction,
+};
+use bevy_mod_picking::highlight::Highlighting;
+use futures_lite::future;
+
+use crate::{
+    assets::GameAssets,
+    simulation::{Action, Simulation},
+    solver::{solve, SolverOptions, SolverResult, TimedAction},
+};
+
+use super::{history::SimulationRestored, level::LevelSimulation, rod::MovableRod};
+
+/// The search runs in the background, but an answer is only useful within a few seconds. The campaign levels need
+/// far fewer states.
+const HINT_MAX_STATES: usize = 100_000;
+
+/// Progress of the last hint request
+#[derive(Default)]
+pub enum HintStatus {
+    #[default]
+    None,
+    /// Solving the level from the state at the time of the request
+    Searching(Task<SolverResult>),
+    /// The next action is found, but its tick is not reached yet
+    Waiting(TimedAction),
+    /// The rod of the next action is highlighted
+    Shown,
+    /// The climbers win without any action
+    NoMoveNeeded,
+    /// The search ended without a solution. `dead_end` when it proved that no state reachable from the request wins,
+    /// else it only acted every few ticks or gave up at [`HINT_MAX_STATES`].
+    NotFound { dead_end: bool },
+}
+
+/// Hints requested by the player on the current level
+#[derive(Resource, Default)]
+pub struct LevelHints {
+    pub used: u32,
+    pub status: HintStatus,
+}
+
+/// Marks the movable rod suggested by the last hint
+#[derive(Component, Clone, Debug)]
+pub struct RodHint;
+
+type HintedRodQueryItem<'a> = (
+    Entity,
+    &'a MovableRod,
+    &'a Interaction,
+    &'a mut Highlighting<StandardMaterial>,
+    &'a mut Handle<StandardMaterial>,
+);
+
+fn set_rod_material(
+    material: Handle<StandardMaterial>,
+    interaction: &Interaction,
+    highlighting: &mut Highlighting<StandardMaterial>,
+    current_material: &mut Handle<StandardMaterial>,
+) {
+    // The picking highlighting restores `initial` when the rod is no longer hovered
+    highlighting.initial = material.clone();
+    if *interaction == Interaction::None {
+        *current_material = material;
+    }
+}
+
+fn start_hint_search(simulation: &Simulation) -> Task<SolverResult> {
+    let simulation = simulation.clone();
+    let options = SolverOptions {
+        max_states: HINT_MAX_STATES,
+        ..Default::default()
+    };
+    AsyncComputeTaskPool::get().spawn(async move { solve(&simulation, &options) })
+}
+
+/// Starts solving the level from its current state, unless the last hint is still pending or shown. A search that
+/// found nothing can be retried, the climbers having moved since.
+pub fn handle_hint_key(
+    keyboard_input: Res<Input<KeyCode>>,
+    simulation: Res<LevelSimulation>,
+    mut hints: ResMut<LevelHints>,
+) {
+    if !keyboard_input.just_pressed(KeyCode::H)
+        || !matches!(hints.status, HintStatus::None | HintStatus::NotFound { .. })
+    {
+        return;
+    }
+    hints.status = HintStatus::Searching(start_hint_search(&simulation.0));
+}
+
+/// Collects the result of the hint search. The search starts over when the simulation went past the first action
+/// of the solution while searching.
+pub fn poll_hint_search(simulation: Res<LevelSimulation>, mut hints: ResMut<LevelHints>) {
+    let HintStatus::Searching(task) = &mut hints.bypass_change_detection().status else {
+        return;
+    };
+    if !task.is_finished() {
+        return;
+    }
+    let solution = match future::block_on(task) {
+        SolverResult::Solved(solution) => solution,
+        SolverResult::Unsolvable { .. } => {
+            info!("No solution from the current state, the level has to be restarted");
+            hints.status = HintStatus::NotFound { dead_end: true };
+            return;
+        }
+        // Not a proof, acting at other ticks may still win
+        SolverResult::NoSolutionAtInterval { .. } => {
+            info!("No hint found when acting every few ticks");
+            hints.status = HintStatus::NotFound { dead_end: false };
+            return;
+        }
+        SolverResult::Inconclusive { .. } => {
+            info!("No hint found");
+            hints.status = HintStatus::NotFound { dead_end: false };
+            return;
+        }
+    };
+    hints.status = match solution.actions.into_iter().next() {
+        None => HintStatus::NoMoveNeeded,
+        Some(next_action) if next_action.tick < simulation.0.tick() => {
+            HintStatus::Searching(start_hint_search(&simulation.0))
+        }
+        Some(next_action) => {
+            hints.used += 1;
+            HintStatus::Waiting(next_action)
+        }
+    };
+}
+
+/// Highlights the rod of the hint once the tick of its action is reached
+pub fn show_rod_hint(
+    mut commands: Commands,
+    simulation: Res<LevelSimulation>,
+    mut hints: ResMut<LevelHints>,
+    mut rods: Query<HintedRodQueryItem>,
+    hinted_rods: Query<Entity, With<RodHint>>,
+    assets: Res<GameAssets>,
+) {
+    let HintStatus::Waiting(next_action) = &hints.status else {
+        return;
+    };
+    if next_action.tick > simulation.0.tick() {
+        return;
+    }
+
+    for entity in hinted_rods.iter() {
+        commands.entity(entity).remove::<RodHint>();
+        if let Ok((_, _, interaction, mut highlighting, mut material)) = rods.get_mut(entity) {
+            set_rod_material(
+                assets.movable_rod_mat.clone(),
+                interaction,
+                &mut highlighting,
+                &mut material,
+            );
+        }
+    }
+
+    let Action::ToggleRod {
+        pillar,
+        face,
+        position,
+    } = &next_action.action;
+    if let Some((entity, _, interaction, mut highlighting, mut material)) =
+        rods.iter_mut().find(|(_, rod, ..)| {
+            rod.pillar == *pillar && rod.face == *face && rod.position == *position
+        })
+    {
+        commands.entity(entity).insert(RodHint);
+        set_rod_material(
+            assets.movable_rod_hint_mat.clone(),
+            interaction,
+            &mut highlighting,
+            &mut material,
+        );
+    }
+    hints.status = HintStatus::Shown;
+}
+
+/// Removes the hint, or drops its search, as soon as any rod is toggled or the simulation is restored, since it may
+/// not be the best move anymore
+pub fn clear_rod_hint(
+    mut commands: Commands,
+    toggled_rods: Query<(), Changed<MovableRod>>,
+    mut restored_events: EventReader<SimulationRestored>,
+    mut hints: ResMut<LevelHints>,
+    mut hinted_rods: Query<HintedRodQueryItem, With<RodHint>>,
+    assets: Res<GameAssets>,
+) {
+    if toggled_rods.is_empty() && restored_events.iter().count() == 0 {
+        return;
+    }
+    if !matches!(hints.status, HintStatus::None) {
+        hints.status = HintStatus::None;
+    }
+    for (entity, _, interaction, mut highlighting, mut material) in hinted_rods.iter_mut() {
+        commands.entity(entity).remove::<RodHint>();
+        set_rod_material(
+            assets.movable_rod_mat.clone(),
+            interaction,
+            &mut highlighting,
+            &mut material,
+        );
+    }
+}
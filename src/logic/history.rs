@@ -0,0 +1,262 @@
+use std::time::Duration;
+
+use bevy::prelude::{
+    BuildChildren, Commands, Entity, EventReader, EventWriter, Handle, Input, KeyCode, NextState,
+    Query, Res, ResMut, Resource, StandardMaterial, State, Transform, Vec3,
+};
+use bevy_tweening::{
+    lens::{TransformPositionLens, TransformScaleLens},
+    Animator, EaseFunction, Tween,
+};
+
+use crate::{
+    assets::{GameAssets, PYLON_VERTICAL_MOVEMENT_AMPLITUDE},
+    simulation::{Action, ClimberState, Simulation, SimulationStatus},
+    GameState,
+};
+
+use super::{
+    climber::{get_face, Climber, CLIMBER_TRANSLATION_ON_PYLON},
+    face::Face,
+    level::{GameLevels, LevelCompletion, LevelSimulation},
+    rod::{get_movable_rod_translation, MovableRod},
+    Pylon,
+};
+
+struct HistoryEntry {
+    /// State right before the action was applied
+    before: Simulation,
+    action: Action,
+}
+
+/// Player actions of the current level, with the simulation state they were applied to
+#[derive(Resource, Default)]
+pub struct LevelHistory {
+    undo_stack: Vec<HistoryEntry>,
+    redo_stack: Vec<HistoryEntry>,
+    toggles_count: usize,
+}
+
+impl LevelHistory {
+    /// Actions leading to the current state
+    pub fn actions_count(&self) -> usize {
+        self.undo_stack.len()
+    }
+
+    /// Actions applied during the attempt, redone ones included. Unlike [`Self::actions_count`], it does not go back on
+    /// undo.
+    pub fn toggles_count(&self) -> usize {
+        self.toggles_count
+    }
+
+    /// Actions leading to the current state, with the simulation tick each was applied at
+    pub fn actions(&self) -> impl Iterator<Item = (u64, &Action)> {
+        self.undo_stack
+            .iter()
+            .map(|entry| (entry.before.tick(), &entry.action))
+    }
+
+    /// `before` is the state `action` was successfully applied to
+    pub fn record(&mut self, before: Simulation, action: Action) {
+        self.undo_stack.push(HistoryEntry { before, action });
+        self.redo_stack.clear();
+        self.toggles_count += 1;
+    }
+
+    /// Restores the state preceding the last action. Returns `false` when there is nothing to undo.
+    pub fn undo(&mut self, simulation: &mut Simulation) -> bool {
+        let Some(entry) = self.undo_stack.pop() else {
+            return false;
+        };
+        *simulation = entry.before.clone();
+        self.redo_stack.push(entry);
+        true
+    }
+
+    /// Applies the last undone action again, at the moment it was first applied. Returns `false` when there is nothing
+    /// to redo.
+    pub fn redo(&mut self, simulation: &mut Simulation) -> bool {
+        let Some(entry) = self.redo_stack.pop() else {
+            return false;
+        };
+        *simulation = entry.before.clone();
+        simulation
+            .apply(&entry.action)
+            .expect("A recorded action should apply to the state it was recorded on");
+        self.undo_stack.push(entry);
+        self.toggles_count += 1;
+        true
+    }
+}
+
+/// Sent when [`LevelSimulation`] is replaced by another state, which the level entities have to match
+pub struct SimulationRestored;
+
+pub fn handle_history_keys(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut simulation: ResMut<LevelSimulation>,
+    mut history: ResMut<LevelHistory>,
+    mut restored_events: EventWriter<SimulationRestored>,
+) {
+    if !keyboard_input.any_pressed([KeyCode::LControl, KeyCode::RControl]) {
+        return;
+    }
+    let shift = keyboard_input.any_pressed([KeyCode::LShift, KeyCode::RShift]);
+    let restored = if keyboard_input.just_pressed(KeyCode::Y)
+        || (shift && keyboard_input.just_pressed(KeyCode::Z))
+    {
+        history.redo(&mut simulation.0)
+    } else if keyboard_input.just_pressed(KeyCode::Z) {
+        history.undo(&mut simulation.0)
+    } else {
+        false
+    };
+    if restored {
+        restored_events.send(SimulationRestored);
+    }
+}
+
+fn stop_animation(animator: &mut Animator<Transform>, transform: &mut Transform) {
+    transform.scale = Vec3::ONE;
+    // Dummy tween, replacing any movement in progress
+    animator.set_tweenable(
+        Tween::new(
+            EaseFunction::QuadraticInOut,
+            Duration::from_millis(1),
+            TransformScaleLens {
+                start: Vec3::ONE,
+                end: Vec3::ONE,
+            },
+        )
+        .with_repeat_count(0),
+    );
+}
+
+/// Moves the rods to match a restored [`LevelSimulation`], and resumes the level if it is not lost anymore
+pub fn sync_rods_with_simulation(
+    mut restored_events: EventReader<SimulationRestored>,
+    simulation: Res<LevelSimulation>,
+    mut rods: Query<(&mut MovableRod, &mut Transform, &mut Animator<Transform>)>,
+    state: Res<State<GameState>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if restored_events.iter().count() == 0 {
+        return;
+    }
+
+    for (mut rod, mut transform, mut animator) in rods.iter_mut() {
+        let Some(simulated_rod) = simulation
+            .0
+            .rods()
+            .iter()
+            // Rods on the other axis may share the position, but never the pair of faces
+            .find(|r| {
+                r.pillar == rod.pillar
+                    && r.position == rod.position
+                    && (r.face == rod.face || r.face == rod.face.get_opposite())
+            })
+        else {
+            continue;
+        };
+        // Only flagged as changed when toggled
+        if rod.face != simulated_rod.face {
+            rod.face = simulated_rod.face.clone();
+        }
+        transform.translation = get_movable_rod_translation(&rod.face, transform.translation);
+        // Dummy tween, replacing any movement in progress
+        animator.set_tweenable(
+            Tween::new(
+                EaseFunction::QuadraticInOut,
+                Duration::from_millis(1),
+                TransformPositionLens {
+                    start: transform.translation,
+                    end: transform.translation,
+                },
+            )
+            .with_repeat_count(0),
+        );
+    }
+
+    if state.0 == GameState::Lost && simulation.0.status() == SimulationStatus::Playing {
+        next_state.set(GameState::Playing);
+    }
+}
+
+/// Powers the pylons to match a restored [`LevelSimulation`]
+pub fn sync_pylons_with_simulation(
+    mut commands: Commands,
+    mut restored_events: EventReader<SimulationRestored>,
+    simulation: Res<LevelSimulation>,
+    mut level_completion: ResMut<LevelCompletion>,
+    mut pylons: Query<(
+        &mut Pylon,
+        &mut Transform,
+        &mut Handle<StandardMaterial>,
+        Entity,
+    )>,
+    assets: Res<GameAssets>,
+) {
+    if restored_events.iter().count() == 0 {
+        return;
+    }
+
+    for (mut pylon, mut transform, mut material, pylon_entity) in pylons.iter_mut() {
+        let powered = simulation.0.is_pylon_powered(&pylon.id);
+        pylon.powered = powered;
+        transform.translation = pylon.rest_translation;
+        if powered {
+            transform.translation.y += PYLON_VERTICAL_MOVEMENT_AMPLITUDE;
+            *material = assets.climber_mat.clone();
+        } else {
+            *material = assets.pillar_mat.clone();
+        }
+        // Stops the powering animation
+        commands
+            .entity(pylon_entity)
+            .remove::<Animator<Transform>>();
+    }
+    level_completion.powered_pylons_count = simulation.0.powered_pylons_count() as u8;
+}
+
+/// Moves the climbers to match a restored [`LevelSimulation`]
+pub fn sync_climbers_with_simulation(
+    mut commands: Commands,
+    mut restored_events: EventReader<SimulationRestored>,
+    simulation: Res<LevelSimulation>,
+    game_levels: Res<GameLevels>,
+    mut climbers: Query<(&Climber, &mut Transform, &mut Animator<Transform>, Entity)>,
+    pylons: Query<(&Pylon, Entity)>,
+    faces: Query<&Face>,
+) {
+    if restored_events.iter().count() == 0 {
+        return;
+    }
+    let Some(level_entity) = game_levels.current_level_entity else {
+        return;
+    };
+
+    for (climber, mut transform, mut animator, climber_entity) in climbers.iter_mut() {
+        stop_animation(&mut animator, &mut transform);
+        match &simulation.0.climbers()[climber.index].state {
+            ClimberState::Saved { pylon } => {
+                if let Some((_, pylon_entity)) = pylons.iter().find(|(p, _)| p.id == *pylon) {
+                    commands.entity(pylon_entity).add_child(climber_entity);
+                    transform.translation = CLIMBER_TRANSLATION_ON_PYLON;
+                }
+                continue;
+            }
+            ClimberState::Waiting { on_tile: tile }
+            | ClimberState::Moving { to_tile: tile, .. } => {
+                transform.translation =
+                    get_face(&faces, &tile.face).climber_get_pos_from_tile(tile.position);
+            }
+            ClimberState::Falling { face, i, height } => {
+                transform.translation =
+                    get_face(&faces, face).climber_get_pos_while_falling(*i, *height);
+            }
+            // Stays where it fell
+            ClimberState::Dead => (),
+        }
+        commands.entity(level_entity).add_child(climber_entity);
+    }
+}
@@ -1,13 +1,16 @@
-use std::{collections::HashMap, time::Duration};
+use std::time::Duration;
 
 use bevy::{
     pbr::CascadeShadowConfigBuilder,
     prelude::{
-        default, shape, Assets, BuildChildren, Bundle, Color, Commands, Component,
-        DespawnRecursiveExt, DirectionalLight, DirectionalLightBundle, Entity, EulerRot,
-        EventReader, Mesh, Name, NextState, PbrBundle, Quat, Query, Res, ResMut, Resource,
-        SpatialBundle, Transform, Vec3, With,
+        default, in_state, info, resource_exists, shape, warn, AddAsset, App, AssetEvent, Assets,
+        BuildChildren, Bundle, Color, Commands, Component, CoreSchedule, DespawnRecursiveExt,
+        DetectChanges, DirectionalLight, DirectionalLightBundle, Entity, EulerRot, EventReader,
+        EventWriter, Handle, IntoSystemAppConfig, IntoSystemConfig, Mesh, Name, NextState,
+        OnUpdate, PbrBundle, Plugin, Quat, Query, Ref, Res, ResMut, Resource, SpatialBundle, State,
+        Time, Transform, Vec3, With, World,
     },
+    time::Stopwatch,
     ui::{FocusPolicy, Interaction},
 };
 use bevy_mod_picking::{highlight::Highlight, Hover, PickableMesh};
@@ -15,21 +18,33 @@ use bevy_tweening::{lens::TransformPositionLens, Animator, EaseFunction, Tween};
 
 use crate::{
     assets::{
-        GameAssets, CLIMBER_LEVITATE_DISTANCE, CLIMBER_RADIUS, HALF_ROD_WIDTH, HALF_TILE_SIZE,
-        HALF_VISIBLE_ROD_LENGTH, MOVABLE_ROD_MOVEMENT_AMPLITUDE, PYLON_HEIGHT,
-        PYLON_HORIZONTAL_DELTA, PYLON_RADIUS, TILE_SIZE, WIN_PYLON_ANIMATION_DURATION,
-        WIN_PYLON_HEIGHT,
+        GameAssets, HALF_TILE_SIZE, HALF_VISIBLE_ROD_LENGTH, MOVABLE_ROD_MOVEMENT_AMPLITUDE,
+        PYLON_HEIGHT, PYLON_HORIZONTAL_DELTA, PYLON_RADIUS, TILE_SIZE,
+        WIN_PYLON_ANIMATION_DURATION, WIN_PYLON_HEIGHT,
     },
-    data::{FaceDirection, FaceSize, LevelData, TileDataType},
+    camera::LevelFraming,
+    data::{
+        Campaign, CampaignLevel, CampaignLoader, FaceDirection, FaceSize, LevelData,
+        LevelDataLoader, TileDataType, UnlockRequirement,
+    },
+    replay::{play_replay_actions, restart_replay_playback, ReplayPlayback},
+    settings::Settings,
+    simulation::{PylonId, Simulation, TilePosition},
     GameState,
 };
 
 use super::{
-    climber::{spawn_climber, ClimberEvent},
-    face::Face,
+    climber::{spawn_climber, update_climbers, PylonPowered},
+    face::{get_face_offset, Face},
+    handle_win_pylon_pick_events,
+    hint::LevelHints,
+    history::{
+        handle_history_keys, sync_climbers_with_simulation, sync_pylons_with_simulation,
+        sync_rods_with_simulation, LevelHistory, SimulationRestored,
+    },
     pillar::{spawn_pillar, Pillar},
     rod::{spawn_movable_rod, spawn_static_rod},
-    Pylon, TilePosition, TileType, WinPylon,
+    Pylon, WinPylon,
 };
 
 #[derive(Component, Default)]
@@ -55,6 +70,8 @@ impl Levelbundle {
 pub enum LevelEvent {
     Reload,
     LoadNext,
+    /// Loads the campaign level with the given id
+    Load(String),
 }
 
 #[derive(Resource)]
@@ -62,9 +79,44 @@ pub struct GameLevels {
     pub current_level_entity: Option<Entity>,
 
     current_level_idx: usize,
-    level_builders: Vec<fn() -> LevelData>,
+    campaign: Handle<Campaign>,
+    /// Campaign level id, or level number counting from 1, to start from once the campaign is loaded
+    start_level: Option<String>,
+    /// See [`GameLevels::single_level`]
+    single_level: bool,
+}
+
+/// Sent once a level is spawned, with the id of its campaign level
+#[derive(Clone, Debug)]
+pub struct LevelLoaded {
+    pub level: Entity,
+    pub level_id: String,
 }
 
+/// Every pylon of the level is powered
+#[derive(Clone, Debug)]
+pub struct LevelWon;
+
+/// A climber died
+#[derive(Clone, Debug)]
+pub struct LevelLost;
+
+/// Rules state of the current level
+#[derive(Resource)]
+pub struct LevelSimulation(pub Simulation);
+
+/// Directional light casting the shadows of the level, when enabled in the settings
+#[derive(Component, Clone, Debug)]
+pub struct ShadowCastingLight;
+
+/// Rod toggles needed by a good player, `None` when unknown
+#[derive(Resource)]
+pub struct LevelPar(pub Option<usize>);
+
+/// Time spent playing the current attempt of the level. Unlike the simulation tick, it does not go back on undo.
+#[derive(Resource, Default)]
+pub struct LevelStopwatch(pub Stopwatch);
+
 #[derive(Resource)]
 pub struct LevelCompletion {
     pub pylons_count: u8,
@@ -77,87 +129,276 @@ impl LevelCompletion {
 }
 
 impl GameLevels {
-    pub fn new(level_builders: Vec<fn() -> LevelData>) -> Self {
+    /// Starts at `start_level`, a level id or a level number counting from 1, if it is part of the campaign. Else
+    /// starts at its first level.
+    pub fn new(campaign: Handle<Campaign>, start_level: Option<String>) -> Self {
         Self {
             current_level_idx: 0,
             current_level_entity: None,
-            level_builders,
+            campaign,
+            start_level,
+            single_level: false,
         }
     }
-    pub fn advance_level(&mut self) {
-        self.current_level_idx = (self.current_level_idx + 1) % self.level_builders.len();
+
+    /// A campaign made of `level` alone, with `level_id` as its id. The level and campaign assets are added to `world`.
+    pub fn single_level(world: &mut World, level_id: &str, level: LevelData) -> Self {
+        let data = world.resource_mut::<Assets<LevelData>>().add(level);
+        let campaign = world.resource_mut::<Assets<Campaign>>().add(Campaign {
+            chapters: vec![level_id.to_string()],
+            levels: vec![CampaignLevel {
+                id: level_id.to_string(),
+                chapter: 0,
+                unlock: UnlockRequirement::Unlocked,
+                data,
+            }],
+            completes_campaign: true,
+        });
+        Self {
+            single_level: true,
+            ..Self::new(campaign, None)
+        }
+    }
+
+    /// `true` for a level played on its own, outside of the game campaign
+    pub fn is_single_level(&self) -> bool {
+        self.single_level
     }
 
-    pub fn get_current_level_data(&self) -> LevelData {
-        self.level_builders[self.current_level_idx]()
+    fn resume(&mut self, campaign: &Campaign) {
+        let Some(start_level) = self.start_level.take() else {
+            return;
+        };
+        if self.select_level(campaign, &start_level) {
+            return;
+        }
+        match start_level.parse::<usize>() {
+            Ok(level_number) if (1..=campaign.levels.len()).contains(&level_number) => {
+                self.current_level_idx = level_number - 1;
+            }
+            _ => warn!("No level \"{start_level}\" in the campaign, starting at the first level"),
+        }
+    }
+
+    /// Returns `false` when there is no next level because the campaign is completed
+    pub fn advance_level(&mut self, campaign: &Campaign) -> bool {
+        if self.current_level_idx + 1 < campaign.levels.len() {
+            self.current_level_idx += 1;
+        } else if campaign.completes_campaign {
+            return false;
+        } else {
+            self.current_level_idx = 0;
+        }
+        true
+    }
+
+    /// Returns `None` while the campaign file is still being loaded
+    pub fn get_current_level_handle<'a>(
+        &self,
+        campaigns: &'a Assets<Campaign>,
+    ) -> Option<&'a Handle<LevelData>> {
+        let campaign = campaigns.get(&self.campaign)?;
+        Some(&campaign.levels.get(self.current_level_idx)?.data)
+    }
+
+    /// Returns `false` when the campaign has no such level
+    fn select_level(&mut self, campaign: &Campaign, level_id: &str) -> bool {
+        match campaign.levels.iter().position(|l| l.id == level_id) {
+            Some(level_idx) => {
+                self.current_level_idx = level_idx;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn campaign(&self) -> &Handle<Campaign> {
+        &self.campaign
+    }
+
+    /// Returns `None` while the campaign file is still being loaded
+    pub fn get_current_level_id<'a>(&self, campaigns: &'a Assets<Campaign>) -> Option<&'a str> {
+        let campaign = campaigns.get(&self.campaign)?;
+        Some(&campaign.levels.get(self.current_level_idx)?.id)
+    }
+
+    /// Returns `None` while the campaign or the level files are still being loaded
+    pub fn get_current_level_data<'a>(
+        &self,
+        campaigns: &Assets<Campaign>,
+        level_assets: &'a Assets<LevelData>,
+    ) -> Option<&'a LevelData> {
+        level_assets.get(self.get_current_level_handle(campaigns)?)
     }
 }
 
 pub fn level_event_handler(
     mut level_events: EventReader<LevelEvent>,
     mut commands: Commands,
-    meshes: ResMut<Assets<Mesh>>,
     mut game_levels: ResMut<GameLevels>,
-    assets: Res<GameAssets>,
+    campaigns: Res<Assets<Campaign>>,
     mut next_state: ResMut<NextState<GameState>>,
 ) {
-    for event in level_events.iter() {
-        match event {
-            LevelEvent::Reload | LevelEvent::LoadNext => {
-                if let Some(level_entity) = game_levels.current_level_entity.take() {
-                    commands.entity(level_entity).despawn_recursive();
-                }
-                match event {
-                    LevelEvent::LoadNext => game_levels.advance_level(),
-                    _ => (),
+    if let Some(event) = level_events.iter().next() {
+        let campaign_completed = match event {
+            LevelEvent::Reload => false,
+            LevelEvent::LoadNext => match campaigns.get(&game_levels.campaign) {
+                Some(campaign) => !game_levels.advance_level(campaign),
+                None => false,
+            },
+            LevelEvent::Load(level_id) => {
+                let selected = campaigns
+                    .get(&game_levels.campaign)
+                    .is_some_and(|campaign| game_levels.select_level(campaign, level_id));
+                if !selected {
+                    warn!("No level \"{level_id}\" in the campaign");
+                    level_events.clear();
+                    return;
                 }
-                game_levels.current_level_entity = Some(spawn_level(
-                    &game_levels.get_current_level_data(),
-                    commands,
-                    meshes,
-                    assets,
-                    // materials,
-                ));
-                next_state.set(GameState::Playing);
+                false
             }
+        };
+        if campaign_completed {
+            // Keep the last level displayed behind the ending screen
+            next_state.set(GameState::CampaignCompleted);
+        } else {
+            if let Some(level_entity) = game_levels.current_level_entity.take() {
+                commands.entity(level_entity).despawn_recursive();
+            }
+            // The level itself is spawned by spawn_pending_level, once its data is loaded
+            next_state.set(GameState::Playing);
         }
-        break;
     }
     level_events.clear();
 }
 
+/// Respawns the current level when its file is modified. Requires the asset server to watch for changes.
+pub fn hot_reload_level(
+    mut level_asset_events: EventReader<AssetEvent<LevelData>>,
+    mut level_events: EventWriter<LevelEvent>,
+    game_levels: Res<GameLevels>,
+    campaigns: Res<Assets<Campaign>>,
+) {
+    let Some(current_level_handle) = game_levels.get_current_level_handle(&campaigns) else {
+        level_asset_events.clear();
+        return;
+    };
+    for event in level_asset_events.iter() {
+        if let AssetEvent::Modified { handle } = event {
+            if handle == current_level_handle && game_levels.current_level_entity.is_some() {
+                info!("Level file modified, reloading the level");
+                level_events.send(LevelEvent::Reload);
+            }
+        }
+    }
+}
+
+/// Spawns the current level when none is spawned, as soon as its data is available
+pub fn spawn_pending_level(
+    commands: Commands,
+    meshes: ResMut<Assets<Mesh>>,
+    assets: Res<GameAssets>,
+    campaigns: Res<Assets<Campaign>>,
+    level_assets: Res<Assets<LevelData>>,
+    mut game_levels: ResMut<GameLevels>,
+    mut level_loaded_events: EventWriter<LevelLoaded>,
+) {
+    if game_levels.current_level_entity.is_some() {
+        return;
+    }
+    if let Some(campaign) = campaigns.get(&game_levels.campaign) {
+        game_levels.resume(campaign);
+    }
+    let (Some(level_data), Some(level_id)) = (
+        game_levels.get_current_level_data(&campaigns, &level_assets),
+        game_levels.get_current_level_id(&campaigns),
+    ) else {
+        return;
+    };
+    let level_entity = spawn_level(
+        level_data, commands, meshes, assets,
+        // materials,
+    );
+    game_levels.current_level_entity = Some(level_entity);
+    level_loaded_events.send(LevelLoaded {
+        level: level_entity,
+        level_id: level_id.to_string(),
+    });
+}
+
+/// Restarts the stopwatch with every attempt of a level, and only advances it while playing
+pub fn update_level_stopwatch(
+    mut level_loaded_events: EventReader<LevelLoaded>,
+    state: Res<State<GameState>>,
+    time: Res<Time>,
+    mut stopwatch: ResMut<LevelStopwatch>,
+) {
+    if level_loaded_events.iter().count() > 0 {
+        stopwatch.0.reset();
+    } else if state.0 == GameState::Playing {
+        stopwatch.0.tick(time.delta());
+    }
+}
+
 pub fn climber_event_handler(
     mut commands: Commands,
-    mut climber_events: EventReader<ClimberEvent>,
+    mut pylon_powered_events: EventReader<PylonPowered>,
     mut level_completion: ResMut<LevelCompletion>,
     mut win_pylon: Query<(&mut Transform, Entity), With<WinPylon>>,
     mut next_state: ResMut<NextState<GameState>>,
+    mut level_won_events: EventWriter<LevelWon>,
 ) {
-    for event in climber_events.iter() {
-        match event {
-            ClimberEvent::ReachedTop => {
-                level_completion.powered_pylons_count += 1;
-                if level_completion.is_won() {
-                    let (win_pylon_transform, win_pylon_entity) = win_pylon.single_mut();
-                    let pos = win_pylon_transform.translation;
-                    let tween = Tween::new(
-                        EaseFunction::QuadraticInOut,
-                        Duration::from_millis(WIN_PYLON_ANIMATION_DURATION),
-                        TransformPositionLens {
-                            start: pos,
-                            end: Vec3::new(pos.x, pos.y + TILE_SIZE, pos.z),
-                        },
-                    );
-                    commands
-                        .entity(win_pylon_entity)
-                        .insert(Animator::new(tween));
-                    next_state.set(GameState::Won);
-                }
-            }
+    for _ in pylon_powered_events.iter() {
+        level_completion.powered_pylons_count += 1;
+        if level_completion.is_won() {
+            let (win_pylon_transform, win_pylon_entity) = win_pylon.single_mut();
+            let pos = win_pylon_transform.translation;
+            let tween = Tween::new(
+                EaseFunction::QuadraticInOut,
+                Duration::from_millis(WIN_PYLON_ANIMATION_DURATION),
+                TransformPositionLens {
+                    start: pos,
+                    end: Vec3::new(pos.x, pos.y + TILE_SIZE, pos.z),
+                },
+            );
+            commands
+                .entity(win_pylon_entity)
+                .insert(Animator::new(tween));
+            next_state.set(GameState::Won);
+            level_won_events.send(LevelWon);
         }
     }
 }
 
+pub fn apply_shadow_settings(
+    settings: Res<Settings>,
+    mut lights: Query<(Ref<ShadowCastingLight>, &mut DirectionalLight)>,
+) {
+    for (light_marker, mut light) in lights.iter_mut() {
+        if settings.is_changed() || light_marker.is_added() {
+            light.shadows_enabled = settings.shadows;
+        }
+    }
+}
+
+fn get_level_framing(level_data: &LevelData) -> LevelFraming {
+    let mut min = Vec3::splat(f32::MAX);
+    let mut max = Vec3::splat(f32::MIN);
+    for pillar in level_data.pillars.iter() {
+        let half_width = pillar.w as f32 * TILE_SIZE / 2.;
+        min = min.min(Vec3::new(pillar.x - half_width, 0., pillar.z - half_width));
+        max = max.max(Vec3::new(
+            pillar.x + half_width,
+            pillar.h as f32 * TILE_SIZE,
+            pillar.z + half_width,
+        ));
+    }
+    LevelFraming {
+        center: (min + max) / 2.,
+        radius: (max - min).length() / 2.,
+    }
+}
+
 pub fn spawn_level(
     level_data: &LevelData,
     mut commands: Commands,
@@ -197,7 +438,7 @@ pub fn spawn_level(
             cascade_shadow_config: cascade_shadow_config.clone(),
             ..default()
         })
-        .insert(Name::from("Front directional light"))
+        .insert((Name::from("Front directional light"), ShadowCastingLight))
         .id();
     let dir_light_back = commands
         .spawn(DirectionalLightBundle {
@@ -221,142 +462,128 @@ pub fn spawn_level(
     commands.entity(level_entity).add_child(dir_light);
     commands.entity(level_entity).add_child(dir_light_back);
 
-    for pillar in level_data.pillars.iter() {
-        let pillar_entity = spawn_pillar(&mut commands, &mut meshes, &assets, &pillar);
+    let simulation = Simulation::new(level_data);
+    let mut climber_index = 0;
+    for (pillar_index, pillar) in level_data.pillars.iter().enumerate() {
+        let pillar_entity = spawn_pillar(&mut commands, &mut meshes, &assets, pillar);
+        commands.entity(pillar_entity).insert(Pillar);
         commands.entity(level_entity).add_child(pillar_entity);
 
         let pillar_half_width = pillar.w as f32 * TILE_SIZE / 2.;
         let pillar_half_height = pillar.h as f32 * TILE_SIZE / 2.;
 
-        let win_pylon = commands
-            .spawn((
-                PbrBundle {
-                    mesh: meshes.add(
-                        shape::Cylinder {
-                            radius: pillar_half_width / 3.,
-                            height: WIN_PYLON_HEIGHT,
-                            resolution: 24,
-                            segments: 1,
-                        }
-                        .into(),
-                    ),
-                    material: assets.climber_mat.clone(),
-                    transform: Transform::from_translation(Vec3::new(
-                        0.,
-                        pillar_half_height - 1.05 * WIN_PYLON_HEIGHT / 2.,
-                        0.,
-                    )),
-                    ..default()
-                },
-                WinPylon,
-                Highlight::default(),
-                Hover::default(),
-                FocusPolicy::Block,
-                Interaction::default(),
-                PickableMesh::default(),
-            ))
-            .id();
-        commands.entity(pillar_entity).add_child(win_pylon);
-
-        let face_entities = HashMap::from([
-            (
-                FaceDirection::West,
-                commands.spawn(Name::from("West face")).id(),
-            ),
-            (
-                FaceDirection::North,
-                commands.spawn(Name::from("North face")).id(),
-            ),
-            (
-                FaceDirection::East,
-                commands.spawn(Name::from("East face")).id(),
-            ),
-            (
-                FaceDirection::South,
-                commands.spawn(Name::from("South face")).id(),
-            ),
-        ]);
-        for face_entity in face_entities.values() {
-            commands.entity(pillar_entity).add_child(*face_entity);
+        // A single win pylon per level, on top of its first pillar
+        if pillar_index == 0 {
+            let win_pylon = commands
+                .spawn((
+                    PbrBundle {
+                        mesh: meshes.add(
+                            shape::Cylinder {
+                                radius: pillar_half_width / 3.,
+                                height: WIN_PYLON_HEIGHT,
+                                resolution: 24,
+                                segments: 1,
+                            }
+                            .into(),
+                        ),
+                        material: assets.climber_mat.clone(),
+                        transform: Transform::from_translation(Vec3::new(
+                            0.,
+                            pillar_half_height - 1.05 * WIN_PYLON_HEIGHT / 2.,
+                            0.,
+                        )),
+                        ..default()
+                    },
+                    WinPylon,
+                    Highlight::default(),
+                    Hover::default(),
+                    FocusPolicy::Block,
+                    Interaction::default(),
+                    PickableMesh::default(),
+                ))
+                .id();
+            commands.entity(pillar_entity).add_child(win_pylon);
         }
 
-        let mut unpowered_pylons: HashMap<FaceDirection, Vec<Entity>> = HashMap::from([
-            (FaceDirection::East, vec![]),
-            (FaceDirection::West, vec![]),
-            (FaceDirection::North, vec![]),
-            (FaceDirection::South, vec![]),
-        ]);
-        let mut unpowered_pylons_count: u8 = 0;
-        for (face_direction, face) in pillar.faces.iter() {
-            let &face_entity = face_entities.get(&face_direction).unwrap();
-            let opposite_face_entity = face_entities.get(&face_direction.get_opposite()).unwrap();
-            let factor = match face_direction {
-                FaceDirection::West => -1.,
-                FaceDirection::North => 1., // TODO North south
-                FaceDirection::East => 1.,
-                FaceDirection::South => -1., // TODO North south
+        for face_direction in FaceDirection::ALL {
+            let face_entity = commands
+                .spawn(Name::from(format!("{:?} face", face_direction)))
+                .id();
+            commands.entity(pillar_entity).add_child(face_entity);
+
+            let face = Face {
+                pillar: pillar_index,
+                origin: Vec3::new(pillar.x, 0., pillar.z)
+                    + get_face_offset(&face_direction, pillar_half_width, -pillar_half_width, 0.),
+                direction: face_direction.clone(),
+                size: FaceSize {
+                    w: pillar.w,
+                    h: pillar.h,
+                },
             };
 
-            // move on x axis for west/east
-            // move on z axis for north/south
+            let Some(face_data) = pillar.faces.get(&face_direction) else {
+                commands.entity(face_entity).insert(face);
+                continue;
+            };
 
-            let col = vec![TileType::Void; pillar.h as usize];
-            let mut face_tiles = vec![col; pillar.w as usize];
-            for tile in face.tiles.iter() {
+            for tile in face_data.tiles.iter() {
+                // Relative to pillar position.
+                let along_face = tile.i as f32 * TILE_SIZE + HALF_TILE_SIZE - pillar_half_width;
+                let y = tile.j as f32 * TILE_SIZE + HALF_TILE_SIZE - pillar_half_height; // TODO + HALF_PILLAR_WIDTH ? Where is the origin of the 3d mesh ?
                 let tile_entity = match tile.kind {
-                    // Relative to pillar position.
                     TileDataType::StaticRod => {
-                        face_tiles[tile.i as usize][tile.j as usize] = TileType::StaticRod(false);
+                        let pos = get_face_offset(
+                            &face_direction,
+                            HALF_VISIBLE_ROD_LENGTH + pillar_half_width,
+                            along_face,
+                            y,
+                        );
                         spawn_static_rod(
                             &mut commands,
                             &assets,
-                            factor * (HALF_VISIBLE_ROD_LENGTH + pillar_half_width), // TODO North south
-                            tile.j as f32 * TILE_SIZE + HALF_TILE_SIZE - pillar_half_height, // TODO + HALF_PILLAR_WIDTH ? Where is the origin of the 3d mesh ?
-                            tile.i as f32 * TILE_SIZE + HALF_TILE_SIZE - pillar_half_width,
+                            &face_direction,
+                            pos.x,
+                            pos.y,
+                            pos.z,
                         )
                     }
                     TileDataType::MovableRod => {
-                        face_tiles[tile.i as usize][tile.j as usize] = TileType::MovableRod(false);
+                        let pos = get_face_offset(
+                            &face_direction,
+                            MOVABLE_ROD_MOVEMENT_AMPLITUDE / 2.,
+                            along_face,
+                            y,
+                        );
                         spawn_movable_rod(
                             &mut commands,
                             &assets,
-                            face_entity,
-                            *opposite_face_entity,
+                            pillar_index,
+                            face_direction.clone(),
                             TilePosition {
                                 i: tile.i,
                                 j: tile.j,
                             },
-                            (factor * MOVABLE_ROD_MOVEMENT_AMPLITUDE) / 2., // TODO North south
-                            tile.j as f32 * TILE_SIZE + HALF_TILE_SIZE - pillar_half_height,
-                            tile.i as f32 * TILE_SIZE + HALF_TILE_SIZE - pillar_half_width,
+                            pos.x,
+                            pos.y,
+                            pos.z,
                         )
                     }
                 };
                 commands.entity(pillar_entity).add_child(tile_entity);
             }
 
-            let face_origin = Vec3::new(
-                pillar.x + factor * pillar_half_width,
-                0.,
-                pillar.z - pillar_half_width, // TODO North south
-            );
-
-            let face_climbers_count = face.climbers.len();
+            let face_climbers_count = face_data.climbers.len();
             let pylons_delta = pillar.w as f32 * TILE_SIZE / (face_climbers_count + 1) as f32;
-            for (climber_idx, climber) in face.climbers.iter().enumerate() {
-                // TODO Spawn pylon
-                let pylon_offset = pylons_delta * (climber_idx + 1) as f32;
+            for (pylon_index, climber) in face_data.climbers.iter().enumerate() {
+                let pylon_offset = pylons_delta * (pylon_index + 1) as f32;
                 let pylon_y = pillar_half_height - 0.8 * PYLON_HEIGHT / 2.;
-                let (pylon_x, pylon_z) = match face_direction {
-                    FaceDirection::West | FaceDirection::East => (
-                        factor * (pillar_half_width - PYLON_HORIZONTAL_DELTA),
-                        pillar_half_width - pylon_offset,
-                    ),
-                    FaceDirection::North | FaceDirection::South => (
-                        pillar_half_width - pylon_offset,
-                        factor * (pillar_half_width - PYLON_HORIZONTAL_DELTA),
-                    ),
-                };
+                let pylon_pos = get_face_offset(
+                    &face_direction,
+                    pillar_half_width - PYLON_HORIZONTAL_DELTA,
+                    pillar_half_width - pylon_offset,
+                    pylon_y,
+                );
                 let unpowered_pylon = commands
                     .spawn((
                         PbrBundle {
@@ -370,64 +597,109 @@ pub fn spawn_level(
                                 .into(),
                             ),
                             material: assets.pillar_mat.clone(),
-                            transform: Transform::from_translation(Vec3::new(
-                                pylon_x, pylon_y, pylon_z,
-                            )),
+                            transform: Transform::from_translation(pylon_pos),
                             ..default()
                         },
-                        Pylon { powered: false },
+                        Pylon {
+                            id: PylonId {
+                                pillar: pillar_index,
+                                face: face_direction.clone(),
+                                index: pylon_index,
+                            },
+                            powered: false,
+                            rest_translation: pylon_pos,
+                        },
                     ))
                     .id();
-                unpowered_pylons
-                    .get_mut(&face_direction)
-                    .unwrap()
-                    .push(unpowered_pylon);
-                unpowered_pylons_count += 1;
                 commands.entity(pillar_entity).add_child(unpowered_pylon);
 
                 let climber_entity = spawn_climber(
                     &mut commands,
                     &assets,
-                    face_entity,
-                    pillar_entity,
-                    &climber,
-                    factor * (HALF_VISIBLE_ROD_LENGTH + pillar_half_width),
-                    climber.tile_j as f32 * TILE_SIZE
-                        + HALF_TILE_SIZE
-                        + HALF_ROD_WIDTH
-                        + CLIMBER_RADIUS
-                        + CLIMBER_LEVITATE_DISTANCE,
-                    (climber.tile_i as f32) * TILE_SIZE - pillar_half_width + HALF_TILE_SIZE,
+                    climber_index,
+                    face.climber_get_pos_from_tile(TilePosition {
+                        i: climber.tile_i,
+                        j: climber.tile_j,
+                    }),
                 );
+                climber_index += 1;
                 commands.entity(level_entity).add_child(climber_entity);
-
-                let tile_data = face_tiles[climber.tile_i as usize][climber.tile_j as usize];
-                face_tiles[climber.tile_i as usize][climber.tile_j as usize] = match tile_data {
-                    TileType::Void => TileType::Void,
-                    TileType::StaticRod(_) => TileType::StaticRod(true),
-                    TileType::MovableRod(_) => TileType::MovableRod(true),
-                };
             }
 
-            commands.entity(face_entity).insert(Face {
-                origin: face_origin,
-                direction: face_direction.clone(),
-                size: FaceSize {
-                    w: pillar.w,
-                    h: pillar.h,
-                },
-                tiles: face_tiles,
-            });
+            commands.entity(face_entity).insert(face);
         }
-        commands
-            .entity(pillar_entity)
-            .insert(Pillar { unpowered_pylons });
-
-        commands.insert_resource(LevelCompletion {
-            pylons_count: unpowered_pylons_count,
-            powered_pylons_count: 0,
-        });
     }
 
+    commands.insert_resource(get_level_framing(level_data));
+    commands.insert_resource(LevelCompletion {
+        pylons_count: simulation.pylons_count() as u8,
+        powered_pylons_count: 0,
+    });
+    commands.insert_resource(LevelPar(level_data.par));
+    commands.insert_resource(LevelSimulation(simulation));
+    commands.insert_resource(LevelHints::default());
+    commands.insert_resource(LevelHistory::default());
+
     level_entity
 }
+
+/// Level files and campaigns, the [`GameState`] flow between levels, and the replays. The [`GameLevels`] and
+/// [`GameAssets`] resources have to be inserted by the app, and the picking events are registered by
+/// [`super::rod::RodPlugin`].
+pub struct LevelPlugin;
+
+impl Plugin for LevelPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<LevelData>()
+            .init_asset_loader::<LevelDataLoader>()
+            .add_asset::<Campaign>()
+            .init_asset_loader::<CampaignLoader>();
+
+        app.add_state::<GameState>()
+            .add_event::<LevelEvent>()
+            .add_event::<LevelLoaded>()
+            .add_event::<LevelWon>()
+            .add_event::<LevelLost>()
+            .add_event::<SimulationRestored>()
+            .init_resource::<LevelHints>()
+            .init_resource::<LevelStopwatch>();
+
+        app.add_system(hot_reload_level.before(level_event_handler))
+            .add_system(update_level_stopwatch.after(spawn_pending_level))
+            .add_system(level_event_handler)
+            .add_system(spawn_pending_level.after(level_event_handler))
+            .add_system(
+                restart_replay_playback
+                    .after(spawn_pending_level)
+                    .run_if(resource_exists::<ReplayPlayback>()),
+            )
+            .add_system(
+                play_replay_actions
+                    .in_schedule(CoreSchedule::FixedUpdate)
+                    .before(update_climbers)
+                    .run_if(in_state(GameState::Playing))
+                    .run_if(resource_exists::<LevelSimulation>())
+                    .run_if(resource_exists::<ReplayPlayback>()),
+            )
+            .add_system(
+                sync_rods_with_simulation
+                    .after(handle_history_keys)
+                    .run_if(resource_exists::<LevelSimulation>()),
+            )
+            .add_system(
+                sync_pylons_with_simulation
+                    .after(handle_history_keys)
+                    .run_if(resource_exists::<LevelCompletion>()),
+            )
+            .add_system(
+                sync_climbers_with_simulation
+                    .after(handle_history_keys)
+                    .run_if(resource_exists::<LevelSimulation>()),
+            )
+            .add_system(
+                handle_win_pylon_pick_events
+                    .in_set(OnUpdate(GameState::Won))
+                    .run_if(resource_exists::<LevelCompletion>()),
+            );
+    }
+}
@@ -1,5 +1,3 @@
-use std::collections::HashMap;
-
 use bevy::{
     prelude::{
         default, shape, Assets, Commands, Component, Entity, Mesh, Name, PbrBundle, Res, ResMut,
@@ -11,45 +9,11 @@ use bevy_mod_picking::PickableMesh;
 
 use crate::{
     assets::{GameAssets, TILE_SIZE},
-    data::{FaceDirection, PillarData},
+    data::PillarData,
 };
 
-#[derive(Clone, Debug)]
-pub struct PillarFace {
-    // pub size: FaceSize,
-    // pub tiles: Vec<Vec<TileType>>,
-}
-
 #[derive(Component, Clone, Debug)]
-pub struct Pillar {
-    // pub faces: Vec<PillarFace>,
-    pub unpowered_pylons: HashMap<FaceDirection, Vec<Entity>>,
-}
-
-impl Pillar {
-    pub fn get_pylon_from_face(&mut self, dir: &FaceDirection) -> Option<Entity> {
-        let pylons = self.unpowered_pylons.get_mut(&dir).unwrap();
-        if pylons.len() > 0 {
-            return Some(pylons.pop().unwrap());
-        }
-        None
-    }
-
-    pub fn pop_first_available_pylon(&mut self) -> Option<Entity> {
-        for dir in [
-            FaceDirection::East,
-            FaceDirection::West,
-            FaceDirection::South,
-            FaceDirection::North,
-        ] {
-            let pylons = self.unpowered_pylons.get_mut(&dir).unwrap();
-            if pylons.len() > 0 {
-                return Some(pylons.pop().unwrap());
-            }
-        }
-        None
-    }
-}
+pub struct Pillar;
 
 pub fn spawn_pillar(
     commands: &mut Commands,
@@ -1,8 +1,10 @@
-use std::time::Duration;
+use std::{f32::consts::FRAC_PI_2, time::Duration};
 
 use bevy::{
+    ecs::system::SystemParam,
     prelude::{
-        default, Commands, Component, Entity, EventReader, Name, PbrBundle, Query, Res, Transform,
+        default, not, resource_exists, App, Commands, Component, Entity, EventReader, EventWriter,
+        IntoSystemConfig, Name, OnUpdate, PbrBundle, Plugin, Quat, Query, Res, ResMut, Transform,
         Vec3,
     },
     ui::{FocusPolicy, Interaction},
@@ -10,34 +12,121 @@ use bevy::{
 use bevy_mod_picking::{highlight::Highlight, Hover, PickableMesh, PickingEvent, SelectionEvent};
 use bevy_tweening::{lens::TransformPositionLens, Animator, EaseFunction, Tween};
 
-use crate::assets::GameAssets;
+use crate::{
+    assets::{GameAssets, MOVABLE_ROD_MOVEMENT_AMPLITUDE},
+    data::FaceDirection,
+    replay::ReplayPlayback,
+    simulation::{Action, TilePosition},
+    GameState,
+};
 
-use super::{face::Face, TilePosition, TileType};
+use super::{
+    face::get_face_factor,
+    hint::clear_rod_hint,
+    history::{sync_rods_with_simulation, LevelHistory},
+    level::LevelSimulation,
+};
 
 #[derive(Component, Clone, Debug)]
 pub struct MovableRod {
-    pub face: Entity,
-    pub opposite_face: Entity,
+    pub pillar: usize,
+    /// Face on which the rod is currently sticking out
+    pub face: FaceDirection,
     pub position: TilePosition,
 }
-impl MovableRod {
-    pub fn swap_face(&mut self) {
-        let tmp_face = self.face;
-        self.face = self.opposite_face;
-        self.opposite_face = tmp_face;
-    }
-}
 
 #[derive(Component, Clone, Debug)]
 pub struct Rod {}
 
+/// A movable rod was toggled by the player
+#[derive(Clone, Debug)]
+pub struct RodToggled {
+    pub rod: Entity,
+    pub from_face: FaceDirection,
+    pub to_face: FaceDirection,
+}
+
+/// Toggles the movable rods, in the simulation and visually
+#[derive(SystemParam)]
+pub struct RodToggles<'w, 's> {
+    rods: Query<
+        'w,
+        's,
+        (
+            &'static Transform,
+            &'static mut Animator<Transform>,
+            &'static mut MovableRod,
+            Entity,
+        ),
+    >,
+    simulation: ResMut<'w, LevelSimulation>,
+    history: ResMut<'w, LevelHistory>,
+    rod_toggled_events: EventWriter<'w, RodToggled>,
+}
+
+impl<'w, 's> RodToggles<'w, 's> {
+    /// Tick of the level simulation the toggles are applied to
+    pub fn tick(&self) -> u64 {
+        self.simulation.0.tick()
+    }
+
+    /// Returns `false` when `rod_entity` is not a movable rod, or when the simulation refused the toggle
+    pub fn toggle(&mut self, rod_entity: Entity) -> bool {
+        let Ok((rod_transform, mut rod_animator, mut rod, _)) = self.rods.get_mut(rod_entity)
+        else {
+            return false;
+        };
+        let action = Action::ToggleRod {
+            pillar: rod.pillar,
+            face: rod.face.clone(),
+            position: rod.position,
+        };
+        let before = self.simulation.0.clone();
+        // Refused while the rod is still moving
+        // TODO Could reverse it if interacting again while active
+        if self.simulation.0.apply(&action).is_err() {
+            return false;
+        }
+        self.history.record(before, action);
+        let from_face = rod.face.clone();
+        rod.face = rod.face.get_opposite();
+        self.rod_toggled_events.send(RodToggled {
+            rod: rod_entity,
+            from_face,
+            to_face: rod.face.clone(),
+        });
+
+        let start = rod_transform.translation;
+        let end = get_movable_rod_translation(&rod.face, start);
+        let tween = Tween::new(
+            EaseFunction::QuadraticInOut,
+            Duration::from_secs(1),
+            TransformPositionLens { start, end },
+        );
+        rod_animator.set_tweenable(tween);
+        true
+    }
+
+    /// Toggles the rod targeted by `action`. Returns `false` when there is no such rod, or when the simulation refused
+    /// the toggle.
+    pub fn apply(&mut self, action: &Action) -> bool {
+        let Action::ToggleRod {
+            pillar,
+            face,
+            position,
+        } = action;
+        let Some((.., rod_entity)) = self.rods.iter().find(|(_, _, rod, _)| {
+            rod.pillar == *pillar && rod.face == *face && rod.position == *position
+        }) else {
+            return false;
+        };
+        self.toggle(rod_entity)
+    }
+}
+
 pub fn handle_movable_rod_picking_events(
     mut events: EventReader<PickingEvent>,
-    mut rods_animators: Query<(&Transform, &mut Animator<Transform>, &mut MovableRod)>,
-    // mut win_pylon: Query<(), With<WinPylon>>,
-    mut faces: Query<&mut Face>,
-    // level_completion: Res<LevelCompletion>,
-    // mut level_events: EventWriter<LevelEvent>,
+    mut rod_toggles: RodToggles,
 ) {
     for event in events.iter() {
         match event {
@@ -45,56 +134,45 @@ pub fn handle_movable_rod_picking_events(
             PickingEvent::Selection(SelectionEvent::JustDeselected(_)) => {}
             PickingEvent::Hover(_) => {}
             PickingEvent::Clicked(entity) => {
-                if let Ok((rod_transform, mut rod_animator, mut rod)) =
-                    rods_animators.get_mut(*entity)
-                {
-                    if rod_animator.tweenable().progress() >= 1.0 {
-                        // Immediately set void for this face
-                        let mut face = faces
-                            .get_mut(rod.face)
-                            .expect("Rod does not appear to have a Face reference");
-                        face.remove_tile_at(rod.position);
-
-                        let mut opposite_face = faces
-                            .get_mut(rod.opposite_face)
-                            .expect("Rod does not appear to have a Face reference");
-                        // TODO set MovingRod on the other face after a delay (animation duration / 2)
-                        opposite_face.set_tile_at(rod.position, TileType::MovableRod(false));
-
-                        rod.swap_face();
-
-                        // TODO Use another cirteria
-                        let tween = Tween::new(
-                            EaseFunction::QuadraticInOut,
-                            Duration::from_secs(1),
-                            TransformPositionLens {
-                                start: rod_transform.translation,
-                                end: Vec3::new(
-                                    -rod_transform.translation.x,
-                                    rod_transform.translation.y,
-                                    rod_transform.translation.z,
-                                ),
-                            },
-                        );
-                        rod_animator.set_tweenable(tween);
-                    }
-                    // TODO Could reverse it if interacting again while active
-                }
+                rod_toggles.toggle(*entity);
             }
         }
     }
 }
 
+/// Translation of a movable rod sticking out of `face`, keeping the other coordinates of `translation`. Movable rods
+/// slide through their pillar, along the axis the face is looking at.
+pub fn get_movable_rod_translation(face: &FaceDirection, translation: Vec3) -> Vec3 {
+    let outward = get_face_factor(face) * MOVABLE_ROD_MOVEMENT_AMPLITUDE / 2.;
+    match face {
+        FaceDirection::West | FaceDirection::East => {
+            Vec3::new(outward, translation.y, translation.z)
+        }
+        FaceDirection::North | FaceDirection::South => {
+            Vec3::new(translation.x, translation.y, outward)
+        }
+    }
+}
+
+/// Rod meshes are modeled along the x axis
+fn get_rod_rotation(face: &FaceDirection) -> Quat {
+    match face {
+        FaceDirection::West | FaceDirection::East => Quat::IDENTITY,
+        FaceDirection::North | FaceDirection::South => Quat::from_rotation_y(FRAC_PI_2),
+    }
+}
+
 pub fn spawn_movable_rod(
     commands: &mut Commands,
     assets: &Res<GameAssets>,
-    face: Entity,
-    opposite_face: Entity,
+    pillar: usize,
+    face: FaceDirection,
     tile_pos: TilePosition,
     x: f32,
     y: f32,
     z: f32,
 ) -> Entity {
+    let rotation = get_rod_rotation(&face);
     // Dummy tween
     let tween = Tween::new(
         EaseFunction::QuadraticInOut,
@@ -111,14 +189,14 @@ pub fn spawn_movable_rod(
             PbrBundle {
                 mesh: assets.movable_rod_mesh.clone(),
                 material: assets.movable_rod_mat.clone(),
-                transform: Transform::from_xyz(x, y, z),
+                transform: Transform::from_xyz(x, y, z).with_rotation(rotation),
                 ..default()
             },
             Rod {},
             MovableRod {
+                pillar,
                 face,
                 position: tile_pos,
-                opposite_face,
             },
             // PickableBundle::default()
             Highlight::default(),
@@ -135,6 +213,7 @@ pub fn spawn_movable_rod(
 pub fn spawn_static_rod(
     commands: &mut Commands,
     assets: &Res<GameAssets>,
+    face: &FaceDirection,
     x: f32,
     y: f32,
     z: f32,
@@ -143,10 +222,33 @@ pub fn spawn_static_rod(
         .spawn((PbrBundle {
             mesh: assets.static_rod_mesh.clone(),
             material: assets.static_rod_mat.clone(),
-            transform: Transform::from_xyz(x, y, z), //.with_rotation(Quat::from_axis_angle(Vec3::Y, PI/2.)
+            transform: Transform::from_xyz(x, y, z).with_rotation(get_rod_rotation(face)),
             ..default()
         },))
         .insert(Rod {})
         .insert(Name::from("Static Rod"))
         .id()
 }
+
+/// Toggles the movable rods clicked by the player. Requires [`LevelPlugin`].
+///
+/// [`LevelPlugin`]: super::level::LevelPlugin
+pub struct RodPlugin;
+
+impl Plugin for RodPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<RodToggled>().add_event::<PickingEvent>();
+
+        app.add_system(
+            handle_movable_rod_picking_events
+                .in_set(OnUpdate(GameState::Playing))
+                .run_if(resource_exists::<LevelSimulation>())
+                .run_if(not(resource_exists::<ReplayPlayback>())),
+        )
+        .add_system(
+            clear_rod_hint
+                .after(handle_movable_rod_picking_events)
+                .after(sync_rods_with_simulation),
+        );
+    }
+}
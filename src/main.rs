@@ -1,327 +1,244 @@
-use std::{f32::consts::PI, time::Duration};
+use std::path::PathBuf;
 
-use assets::GameAssets;
 use bevy::{
-    app::AppExit,
-    diagnostic::FrameTimeDiagnosticsPlugin,
-    input::common_conditions::input_toggle_active,
-    prelude::{
-        default, in_state, shape, Added, App, Assets, BuildChildren, Color, Commands, Component,
-        CoreSchedule, EventReader, EventWriter, Input, IntoSystemAppConfig, IntoSystemConfig,
-        KeyCode, Mesh, Name, NodeBundle, OnEnter, OnUpdate, PbrBundle, PluginGroup, Quat, Query,
-        Res, ResMut, Resource, StandardMaterial, States, TextBundle, Transform, Vec3, Visibility,
-        With,
-    },
-    text::{Text, TextSection, TextStyle},
-    ui::{AlignItems, JustifyContent, PositionType, Size, Style, UiRect, Val},
-    window::{PresentMode, Window, WindowCloseRequested, WindowPlugin},
+    asset::AssetPlugin,
+    prelude::{default, App, NextState, PluginGroup},
+    window::{PresentMode, Window, WindowMode, WindowPlugin},
     DefaultPlugins,
 };
 
-use bevy_mod_picking::{DefaultHighlighting, DefaultPickingPlugins};
-use bevy_tweening::{lens::TextColorLens, Animator, EaseFunction, Tween, TweeningPlugin};
-use camera::{setup_camera, CustomOrbitCameraPlugin};
-use data::{level_1, level_2, level_3, level_4, test_level_data, LevelData};
-use grass::setup_grass;
-use logic::{
-    climber::{update_climbers, ClimberEvent},
-    face::Face,
-    handle_win_pylon_pick_events,
-    level::{
-        climber_event_handler, level_event_handler, spawn_level, GameLevels, LevelEvent, LevelName,
-    },
-    pillar::Pillar,
-    rod::handle_movable_rod_picking_events,
-    Pylon, TilePosition, TileType,
+use bevy_jam_3::{
+    generator::{generate_level, GeneratorParams},
+    logic::level::GameLevels,
+    replay::{load_replay_file, ReplayPlayback},
+    tools::{self, load_level_file},
+    GameState, PillarClimbPlugin, CAMPAIGN, WINDOW_TITLE,
 };
-use smooth_bevy_cameras::LookTransformPlugin;
-use warbler_grass::warblers_plugin::WarblersPlugin;
 
-#[cfg(debug_assertions)]
-use bevy_inspector_egui::quick::WorldInspectorPlugin;
-#[cfg(debug_assertions)]
-use debug::display_stats_ui;
-#[cfg(debug_assertions)]
-use debug::EguiInputBlockerPlugin;
-
-mod assets;
-mod camera;
-mod data;
-mod grass;
-mod logic;
-
-#[cfg(debug_assertions)]
-mod debug;
-
-#[derive(Resource, Default)]
-pub struct EguiBlockInputState {
-    pub wants_keyboard_input: bool,
-    pub wants_pointer_input: bool,
+const USAGE: &str = "\
+Usage: bevy-jam-3 [options]
+
+Options:
+  --level <id or number>   Starts at a campaign level, by id or by number counting from 1
+  --level-file <path>      Plays a level file on its own
+  --seed <seed>            Plays the level generated from the seed
+  --replay <path>          Plays a replay file back
+  --size <width>x<height>  Size of the window, 800x600 by default
+  --fullscreen             Borderless fullscreen window
+  --no-vsync               Disables vsync
+  --hot-reload             Reloads the level files when they change on disk, always on in debug builds
+  --dev-tools              World inspector (F2), FPS counter (F3) and level skipping (N). Requires the dev-tools
+                           feature.
+  --solve <level>          Prints the solution of a level file or campaign level id, or proves it unsolvable,
+                           without starting the game
+  --generate <seed>        Prints the level generated from the seed and its solution, without starting the game
+  --help                   Prints this message";
+
+const DEFAULT_WINDOW_SIZE: (f32, f32) = (800., 600.);
+
+/// Where the game starts, instead of the main menu
+enum Start {
+    CampaignLevel(String),
+    LevelFile(PathBuf),
+    GeneratedLevel(u64),
+    Replay(PathBuf),
 }
 
-#[derive(Default, Clone, Eq, PartialEq, Debug, Hash, States)]
-pub enum GameState {
-    #[default]
-    Playing,
-    Lost,
-    Won,
+/// Tools run instead of the game
+enum Command {
+    Solve(String),
+    Generate(u64),
 }
 
-// THEMES
-
-// Pillar material 193, 109, 0, 255
-// Front dir light WHITE
-
-// Autumn
-// Ambient 255, 68, 0, 255  Brightness 0.2
-// Clear color 109, 241, 255, 255
-// Grass MC 149, 45, 0, 255
-// Grass BC 34, 6, 6, 255
-// Ground 79, 30, 0, 255
-
-// Fushia
-
-pub const CAMERA_CLEAR_COLOR: Color = Color::rgb(0.56, 1., 0.98); // 144, 255, 253, 255
-
-const WINDOW_TITLE: &str = "Bevy-jam-3";
-
-pub fn exit_on_window_close_system(
-    mut app_exit_events: EventWriter<AppExit>,
-    mut window_close_requested_events: EventReader<WindowCloseRequested>,
-) {
-    if !window_close_requested_events.is_empty() {
-        app_exit_events.send(AppExit);
-        window_close_requested_events.clear();
-    }
+#[derive(Default)]
+struct Options {
+    command: Option<Command>,
+    start: Option<Start>,
+    window_size: Option<(f32, f32)>,
+    fullscreen: bool,
+    no_vsync: bool,
+    hot_reload: bool,
+    dev_tools: bool,
 }
 
-fn skip_level(mut level_events: EventWriter<LevelEvent>, keyboard_input: Res<Input<KeyCode>>) {
-    if keyboard_input.just_pressed(KeyCode::N) {
-        level_events.send(LevelEvent::LoadNext);
-    }
+fn parse_window_size(size: &str) -> Option<(f32, f32)> {
+    let (width, height) = size.split_once('x')?;
+    let (width, height) = (width.parse().ok()?, height.parse().ok()?);
+    (width > 0. && height > 0.).then_some((width, height))
 }
 
-fn handle_restart_key(
-    mut level_events: EventWriter<LevelEvent>,
-    keyboard_input: Res<Input<KeyCode>>,
-) {
-    if keyboard_input.just_pressed(KeyCode::Space) {
-        level_events.send(LevelEvent::Reload);
+fn parse_options(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut options = Options::default();
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .ok_or_else(|| format!("Missing value for {arg}"))
+        };
+        let start = match arg.as_str() {
+            "--level" => Start::CampaignLevel(value()?),
+            "--level-file" => Start::LevelFile(value()?.into()),
+            "--seed" => {
+                let seed = value()?;
+                Start::GeneratedLevel(seed.parse().map_err(|_| format!("Invalid seed {seed}"))?)
+            }
+            "--replay" => Start::Replay(value()?.into()),
+            "--solve" => {
+                options.command = Some(Command::Solve(value()?));
+                continue;
+            }
+            "--generate" => {
+                let seed = value()?;
+                options.command = Some(Command::Generate(
+                    seed.parse().map_err(|_| format!("Invalid seed {seed}"))?,
+                ));
+                continue;
+            }
+            "--size" => {
+                let size = value()?;
+                options.window_size = Some(
+                    parse_window_size(&size)
+                        .ok_or_else(|| format!("Invalid window size {size}"))?,
+                );
+                continue;
+            }
+            "--fullscreen" => {
+                options.fullscreen = true;
+                continue;
+            }
+            "--no-vsync" => {
+                options.no_vsync = true;
+                continue;
+            }
+            "--hot-reload" => {
+                options.hot_reload = true;
+                continue;
+            }
+            "--dev-tools" => {
+                options.dev_tools = true;
+                continue;
+            }
+            "--help" => {
+                println!("{USAGE}");
+                std::process::exit(0);
+            }
+            _ => return Err(format!("Unknown option {arg}")),
+        };
+        if options.start.replace(start).is_some() {
+            return Err(String::from(
+                "Only one of --level, --level-file, --seed and --replay can be given",
+            ));
+        }
     }
+    Ok(options)
 }
 
-#[derive(Component, Clone, Debug)]
-struct LevelNameUI;
-
-fn handle_new_levels(
-    mut new_level: Query<&LevelName, Added<LevelName>>,
-    mut level_name_ui: Query<&mut Text, With<LevelNameUI>>,
-) {
-    for loaded_level in new_level.iter_mut() {
-        let mut text = level_name_ui.single_mut();
-        text.sections.first_mut().unwrap().value = loaded_level.0.clone();
-    }
+fn exit_with_error(error: impl std::fmt::Display) -> ! {
+    eprintln!("Error: {error}");
+    std::process::exit(1);
 }
 
-#[derive(Component, Clone, Debug)]
-struct GameOverText;
+fn main() {
+    let options = match parse_options(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(error) => {
+            eprintln!("{error}\n\n{USAGE}");
+            std::process::exit(2);
+        }
+    };
+    if let Some(command) = &options.command {
+        let succeeded = match command {
+            Command::Solve(level) => tools::run_solver(level, CAMPAIGN),
+            Command::Generate(seed) => tools::run_generator(*seed),
+        };
+        std::process::exit(if succeeded { 0 } else { 1 });
+    }
+    if options.dev_tools && !cfg!(feature = "dev-tools") {
+        eprintln!("Warning: --dev-tools ignored, the game was built without the dev-tools feature");
+    }
 
-fn setup_scene(
-    mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    assets: Res<GameAssets>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
-    mut game_levels: ResMut<GameLevels>,
-) {
-    // sky
-    // commands.spawn(PbrBundle {
-    //     mesh: meshes.add(Mesh::from(shape::Box::default())),
-    //     material: materials.add(StandardMaterial {
-    //         base_color: Color::hex("888888").unwrap(),
-    //         unlit: true,
-    //         cull_mode: None,
-    //         ..default()
-    //     }),
-    //     transform: Transform::from_scale(Vec3::splat(1_000_000.0)),
-    //     ..default()
-    // });
-    // Dummy tween
-    let tween = Tween::new(
-        EaseFunction::QuadraticInOut,
-        Duration::from_secs(1),
-        TextColorLens {
-            start: Color::WHITE,
-            end: Color::WHITE,
-            section: 0,
-        },
-    )
-    .with_repeat_count(0);
+    let replay_playback = match &options.start {
+        Some(Start::Replay(path)) => Some(
+            load_replay_file(path.clone())
+                .map(ReplayPlayback::new)
+                .unwrap_or_else(|error| {
+                    exit_with_error(format!("failed to load {}: {error}", path.display()))
+                }),
+        ),
+        _ => None,
+    };
+    let start_level = match &options.start {
+        Some(Start::CampaignLevel(level)) => Some(level.clone()),
+        _ => replay_playback
+            .as_ref()
+            .map(|playback| playback.level_id().to_string()),
+    };
 
-    let text_style = TextStyle {
-        font: assets.font.clone(),
-        font_size: 45.0,
-        color: Color::WHITE,
+    // Levels played on their own replace the campaign
+    let single_level = match &options.start {
+        Some(Start::LevelFile(path)) => {
+            let level_id = path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_else(|| String::from("level"));
+            let level = load_level_file(path).unwrap_or_else(|error| exit_with_error(error));
+            Some((level_id, level))
+        }
+        Some(Start::GeneratedLevel(seed)) => {
+            let generated = generate_level(*seed, &GeneratorParams::default())
+                .unwrap_or_else(|error| exit_with_error(error));
+            Some((format!("seed-{seed}"), generated.level))
+        }
+        _ => None,
     };
-    commands
-        .spawn(NodeBundle {
-            style: Style {
-                size: Size::width(Val::Percent(100.0)),
-                align_items: AlignItems::Center,
-                justify_content: JustifyContent::Center,
-                ..default()
-            },
-            ..default()
-        })
-        .with_children(|parent| {
-            parent.spawn((
-                TextBundle::from_sections([TextSection::new(
-                    "A climber has fallen. Press Space to restart.",
-                    text_style.clone(),
-                )])
-                .with_style(Style {
-                    position_type: PositionType::Absolute,
-                    justify_content: JustifyContent::Center,
-                    position: UiRect {
-                        bottom: Val::Px(85.0),
-                        ..default()
+
+    let mut app = App::new();
+    app.add_plugins(
+        DefaultPlugins
+            .set(WindowPlugin {
+                primary_window: Some(Window {
+                    title: WINDOW_TITLE.into(),
+                    resolution: options.window_size.unwrap_or(DEFAULT_WINDOW_SIZE).into(),
+                    mode: if options.fullscreen {
+                        WindowMode::BorderlessFullscreen
+                    } else {
+                        WindowMode::Windowed
+                    },
+                    present_mode: if options.no_vsync {
+                        PresentMode::AutoNoVsync
+                    } else {
+                        PresentMode::AutoVsync
                     },
+                    // Tells wasm to resize the window according to the available canvas
+                    fit_canvas_to_parent: true,
+                    // Tells wasm not to override default event handling, like F5, Ctrl+R etc.
+                    prevent_default_event_handling: false,
                     ..default()
                 }),
-                GameOverText,
-                Animator::new(tween),
-            ));
-            parent.spawn((
-                TextBundle::from_sections([TextSection::new("LevelName", text_style)]).with_style(
-                    Style {
-                        position_type: PositionType::Absolute,
-                        justify_content: JustifyContent::Center,
-                        position: UiRect {
-                            top: Val::Px(15.0),
-                            ..default()
-                        },
-                        ..default()
-                    },
-                ),
-                LevelNameUI,
-            ));
-        });
-
-    // Ground TODO : move to level specific
-    commands.spawn((
-        PbrBundle {
-            mesh: meshes.add(Mesh::from(shape::Circle::new(20.))),
-            material: materials.add(StandardMaterial {
-                base_color: Color::rgb(0.23, 0., 0.29), // 59, 0, 75, 255
-                metallic: 0.,
-                reflectance: 0.,
-                // unlit: true,
-                // cull_mode: None,
+                ..default()
+            })
+            .set(AssetPlugin {
+                // Hot-reload of the level files. Not supported on wasm.
+                watch_for_changes: (options.hot_reload || cfg!(debug_assertions))
+                    && !cfg!(target_arch = "wasm32"),
                 ..default()
             }),
-            transform: Transform::from_rotation(Quat::from_axis_angle(Vec3::X, -1. * PI / 2.)),
-            ..default()
-        },
-        Name::new("Ground"),
-    ));
-
-    commands.insert_resource(DefaultHighlighting {
-        hovered: assets.movable_rod_highlight_mat.clone(),
-        pressed: assets.movable_rod_mat.clone(),
-        selected: assets.climber_mat.clone(),
+    )
+    .add_plugin(PillarClimbPlugin {
+        start_level,
+        dev_tools: options.dev_tools,
     });
 
-    // Spawn first level
-    game_levels.current_level_entity = Some(spawn_level(
-        &game_levels.get_current_level_data(),
-        commands,
-        meshes,
-        assets,
-        // materials,
-    ));
-}
-
-fn hide_gameover_ui(mut game_over_ui: Query<&mut Visibility, With<GameOverText>>) {
-    let mut visibility = game_over_ui.single_mut();
-    *visibility = Visibility::Hidden;
-}
-
-fn show_gameover_ui(
-    mut game_over_ui: Query<(&mut Visibility, &mut Animator<Text>), With<GameOverText>>,
-) {
-    let (mut visibility, mut animator) = game_over_ui.single_mut();
-    *visibility = Visibility::Visible;
-
-    let tween = Tween::new(
-        EaseFunction::ExponentialInOut,
-        Duration::from_millis(1000),
-        TextColorLens {
-            start: Color::WHITE.with_a(0.),
-            end: Color::WHITE.with_a(1.),
-            section: 0,
-        },
-    );
-    animator.set_tweenable(tween);
-}
-
-fn main() {
-    let mut app = App::new();
-    app.add_plugins(DefaultPlugins.set(WindowPlugin {
-        primary_window: Some(Window {
-            title: WINDOW_TITLE.into(),
-            resolution: (800., 600.).into(),
-            present_mode: PresentMode::AutoVsync,
-            // Tells wasm to resize the window according to the available canvas
-            fit_canvas_to_parent: true,
-            // Tells wasm not to override default event handling, like F5, Ctrl+R etc.
-            prevent_default_event_handling: false,
-            ..default()
-        }),
-        ..default()
-    }))
-    .add_plugin(TweeningPlugin)
-    .add_plugin(LookTransformPlugin)
-    .add_plugin(CustomOrbitCameraPlugin)
-    .add_plugins(DefaultPickingPlugins)
-    .add_plugin(WarblersPlugin);
-
-    let mut level_builders: Vec<fn() -> LevelData> = vec![level_1, level_2, level_3, level_4];
-    #[cfg(debug_assertions)]
-    {
-        level_builders.push(test_level_data);
+    if let Some((level_id, level)) = single_level {
+        let game_levels = GameLevels::single_level(&mut app.world, &level_id, level);
+        app.insert_resource(game_levels);
     }
-    app.init_resource::<GameAssets>()
-        .insert_resource(GameLevels::new(level_builders));
-
-    app.add_state::<GameState>()
-        .add_event::<LevelEvent>()
-        .add_event::<ClimberEvent>();
-
-    app.add_startup_system(setup_camera)
-        .add_startup_system(setup_scene)
-        .add_startup_system(setup_grass);
 
-    app.add_system(level_event_handler)
-        .add_system(handle_restart_key)
-        .add_system(handle_new_levels)
-        .add_system(exit_on_window_close_system)
-        .add_system(climber_event_handler);
-    app.add_system(hide_gameover_ui.in_schedule(OnEnter(GameState::Playing)))
-        .add_system(show_gameover_ui.in_schedule(OnEnter(GameState::Lost)))
-        .add_system(handle_movable_rod_picking_events.in_set(OnUpdate(GameState::Playing)))
-        .add_system(
-            update_climbers
-                .in_schedule(CoreSchedule::FixedUpdate)
-                .run_if(in_state(GameState::Playing)),
-        )
-        .add_system(handle_win_pylon_pick_events.in_set(OnUpdate(GameState::Won)));
-
-    #[cfg(debug_assertions)]
-    {
-        app.add_plugin(WorldInspectorPlugin::new().run_if(input_toggle_active(true, KeyCode::F2)))
-            .add_plugin(EguiInputBlockerPlugin)
-            .add_plugin(FrameTimeDiagnosticsPlugin::default())
-            .add_system(display_stats_ui.run_if(input_toggle_active(true, KeyCode::F3)))
-            .add_system(skip_level);
+    if let Some(playback) = replay_playback {
+        app.insert_resource(playback);
+    }
+    if options.start.is_some() {
+        // Straight to the level, skipping the main menu
+        app.insert_resource(NextState(Some(GameState::Playing)));
     }
 
     app.run();
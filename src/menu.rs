@@ -0,0 +1,173 @@
+use bevy::{
+    app::AppExit,
+    prelude::{
+        default, BuildChildren, ButtonBundle, Changed, Color, Commands, Component,
+        DespawnRecursiveExt, Entity, EventWriter, Input, KeyCode, NextState, NodeBundle, Or, Query,
+        Res, ResMut, State, TextBundle, Visibility, With, Without,
+    },
+    text::TextStyle,
+    ui::{
+        AlignItems, BackgroundColor, FlexDirection, Interaction, JustifyContent, PositionType,
+        Size, Style, UiRect, Val, ZIndex,
+    },
+};
+
+use crate::{
+    assets::GameAssets,
+    level_select::{LevelSelectUI, BUTTON_COLOR, BUTTON_HOVERED_COLOR},
+    settings::SettingsUI,
+    GameState, WINDOW_TITLE,
+};
+
+/// Root of the main menu and of the pause menu
+#[derive(Component, Clone, Debug)]
+pub struct MenuUI;
+
+#[derive(Component, Clone, Copy, Debug)]
+pub enum MenuButton {
+    Continue,
+    LevelSelect,
+    Settings,
+    Quit,
+}
+
+impl MenuButton {
+    fn label(&self) -> &'static str {
+        match self {
+            MenuButton::Continue => "Continue",
+            MenuButton::LevelSelect => "Select a level",
+            MenuButton::Settings => "Settings",
+            MenuButton::Quit => "Quit",
+        }
+    }
+}
+
+pub fn spawn_menu(mut commands: Commands, assets: Res<GameAssets>, state: Res<State<GameState>>) {
+    let title = match state.0 {
+        GameState::Paused => "Paused",
+        _ => WINDOW_TITLE,
+    };
+    let text_style = TextStyle {
+        font: assets.font.clone(),
+        font_size: 45.0,
+        color: Color::WHITE,
+    };
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                    position_type: PositionType::Absolute,
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                background_color: Color::rgba(0., 0., 0., 0.4).into(),
+                // Below the level selection
+                z_index: ZIndex::Global(5),
+                ..default()
+            },
+            // Blocks the picking of the level behind the menu
+            Interaction::default(),
+            MenuUI,
+        ))
+        .with_children(|parent| {
+            parent.spawn(
+                TextBundle::from_section(title, text_style.clone()).with_style(Style {
+                    margin: UiRect::bottom(Val::Px(20.0)),
+                    ..default()
+                }),
+            );
+            for button in [
+                MenuButton::Continue,
+                MenuButton::LevelSelect,
+                MenuButton::Settings,
+                MenuButton::Quit,
+            ] {
+                parent
+                    .spawn((
+                        ButtonBundle {
+                            style: Style {
+                                size: Size::width(Val::Px(250.0)),
+                                margin: UiRect::all(Val::Px(5.0)),
+                                justify_content: JustifyContent::Center,
+                                ..default()
+                            },
+                            background_color: BUTTON_COLOR.into(),
+                            ..default()
+                        },
+                        button,
+                    ))
+                    .with_children(|parent| {
+                        parent.spawn(TextBundle::from_section(
+                            button.label(),
+                            TextStyle {
+                                font_size: 30.0,
+                                ..text_style.clone()
+                            },
+                        ));
+                    });
+            }
+        });
+}
+
+pub fn despawn_menu(mut commands: Commands, menu_ui: Query<Entity, With<MenuUI>>) {
+    for entity in menu_ui.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+type OverlayFilter = Or<(With<LevelSelectUI>, With<SettingsUI>)>;
+
+/// Closes the open overlay if any, else pauses or resumes the game
+pub fn handle_pause_key(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut overlays: Query<&mut Visibility, OverlayFilter>,
+    state: Res<State<GameState>>,
+    mut next_state: ResMut<NextState<GameState>>,
+) {
+    if !keyboard_input.just_pressed(KeyCode::Escape) {
+        return;
+    }
+    let mut closed_overlay = false;
+    for mut visibility in overlays.iter_mut() {
+        if *visibility != Visibility::Hidden {
+            *visibility = Visibility::Hidden;
+            closed_overlay = true;
+        }
+    }
+    if closed_overlay {
+        return;
+    }
+    match state.0 {
+        GameState::Playing => next_state.set(GameState::Paused),
+        GameState::Paused => next_state.set(GameState::Playing),
+        _ => (),
+    }
+}
+
+pub fn handle_menu_buttons(
+    mut buttons: Query<(&Interaction, &mut BackgroundColor, &MenuButton), Changed<Interaction>>,
+    mut level_select_ui: Query<&mut Visibility, (With<LevelSelectUI>, Without<SettingsUI>)>,
+    mut settings_ui: Query<&mut Visibility, With<SettingsUI>>,
+    mut next_state: ResMut<NextState<GameState>>,
+    mut app_exit_events: EventWriter<AppExit>,
+) {
+    for (interaction, mut background_color, button) in buttons.iter_mut() {
+        match interaction {
+            Interaction::Clicked => match button {
+                MenuButton::Continue => next_state.set(GameState::Playing),
+                // Picking a level starts playing it
+                MenuButton::LevelSelect => {
+                    *level_select_ui.single_mut() = Visibility::Visible;
+                }
+                MenuButton::Settings => *settings_ui.single_mut() = Visibility::Visible,
+                MenuButton::Quit => app_exit_events.send(AppExit),
+            },
+            Interaction::Hovered => *background_color = BUTTON_HOVERED_COLOR.into(),
+            Interaction::None => *background_color = BUTTON_COLOR.into(),
+        }
+    }
+}
@@ -0,0 +1,253 @@
+//! Player progress through the campaign, kept across sessions.
+//!
+//! [`PlayerProgress`] is serialized to RON and written through a [`Storage`]: a file in the platform data folder for the
+//! game, or memory only where there is no filesystem.
+
+use std::{collections::HashMap, fmt, io};
+
+use bevy::prelude::{warn, Added, Assets, Query, Res, ResMut, Resource};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    data::Campaign,
+    logic::{
+        history::LevelHistory,
+        level::{GameLevels, LevelName, LevelStopwatch},
+    },
+    storage::{platform_storage, Storage},
+};
+
+const SAVE_FILE_NAME: &str = "progress.ron";
+
+/// Best results of a completed level
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LevelRecord {
+    /// Rod toggles, undone ones included
+    pub best_moves: usize,
+    /// In milliseconds
+    pub best_time: u64,
+}
+
+#[derive(Resource, Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlayerProgress {
+    /// Completed levels, by campaign level id
+    #[serde(default)]
+    pub completed_levels: HashMap<String, LevelRecord>,
+    /// Campaign level id of the level to resume from
+    #[serde(default)]
+    pub last_played_level: Option<String>,
+}
+
+impl PlayerProgress {
+    pub fn from_ron_bytes(bytes: &[u8]) -> Result<PlayerProgress, ron::error::SpannedError> {
+        ron::de::from_bytes(bytes)
+    }
+
+    pub fn to_ron_string(&self) -> Result<String, ron::Error> {
+        ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::new().struct_names(false))
+    }
+
+    pub fn is_completed(&self, level_id: &str) -> bool {
+        self.completed_levels.contains_key(level_id)
+    }
+
+    /// Moves and time are kept independently, each at its best
+    pub fn record_completion(&mut self, level_id: &str, moves: usize, time: u64) {
+        self.completed_levels
+            .entry(level_id.to_string())
+            .and_modify(|record| {
+                record.best_moves = record.best_moves.min(moves);
+                record.best_time = record.best_time.min(time);
+            })
+            .or_insert(LevelRecord {
+                best_moves: moves,
+                best_time: time,
+            });
+    }
+}
+
+#[derive(Debug)]
+pub enum ProgressError {
+    Io(io::Error),
+    Parse(ron::error::SpannedError),
+    Serialize(ron::Error),
+}
+
+impl fmt::Display for ProgressError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProgressError::Io(error) => write!(f, "{error}"),
+            ProgressError::Parse(error) => write!(f, "invalid save data: {error}"),
+            ProgressError::Serialize(error) => {
+                write!(f, "failed to serialize the progress: {error}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ProgressError {}
+
+/// Storage backend of the [`PlayerProgress`] resource
+#[derive(Resource)]
+pub struct ProgressStore(pub Box<dyn Storage>);
+
+impl ProgressStore {
+    /// The save file of the platform, falling back to memory
+    pub fn for_platform() -> Self {
+        Self(platform_storage(SAVE_FILE_NAME))
+    }
+
+    /// Returns the default progress when nothing was saved yet
+    pub fn load(&self) -> Result<PlayerProgress, ProgressError> {
+        match self.0.read().map_err(ProgressError::Io)? {
+            Some(bytes) => PlayerProgress::from_ron_bytes(&bytes).map_err(ProgressError::Parse),
+            None => Ok(PlayerProgress::default()),
+        }
+    }
+
+    pub fn save(&mut self, progress: &PlayerProgress) -> Result<(), ProgressError> {
+        let content = progress.to_ron_string().map_err(ProgressError::Serialize)?;
+        self.0.write(content.as_bytes()).map_err(ProgressError::Io)
+    }
+
+    /// Starts over from the default progress when the saved one can't be loaded
+    pub fn load_or_default(&self) -> PlayerProgress {
+        self.load().unwrap_or_else(|error| {
+            warn!("Failed to load the player progress: {error}");
+            PlayerProgress::default()
+        })
+    }
+
+    fn save_or_warn(&mut self, progress: &PlayerProgress) {
+        if let Err(error) = self.save(progress) {
+            warn!("Failed to save the player progress: {error}");
+        }
+    }
+}
+
+/// Run condition of the progress and replay saving systems. Levels played on their own are not part of the progress,
+/// and their replays could not be played back from the campaign.
+pub fn is_playing_campaign(game_levels: Res<GameLevels>) -> bool {
+    !game_levels.is_single_level()
+}
+
+/// Remembers the level being played, to resume from it on the next start
+pub fn save_last_played_level(
+    new_level: Query<(), Added<LevelName>>,
+    game_levels: Res<GameLevels>,
+    campaigns: Res<Assets<Campaign>>,
+    mut progress: ResMut<PlayerProgress>,
+    mut store: ResMut<ProgressStore>,
+) {
+    if new_level.is_empty() {
+        return;
+    }
+    let Some(level_id) = game_levels.get_current_level_id(&campaigns) else {
+        return;
+    };
+    if progress.last_played_level.as_deref() != Some(level_id) {
+        progress.last_played_level = Some(level_id.to_string());
+        store.save_or_warn(&progress);
+    }
+}
+
+/// Records the results of the level that was just won, as counted by the HUD: undo does not take them back
+pub fn save_level_completion(
+    game_levels: Res<GameLevels>,
+    campaigns: Res<Assets<Campaign>>,
+    history: Res<LevelHistory>,
+    stopwatch: Res<LevelStopwatch>,
+    mut progress: ResMut<PlayerProgress>,
+    mut store: ResMut<ProgressStore>,
+) {
+    let Some(level_id) = game_levels.get_current_level_id(&campaigns) else {
+        return;
+    };
+    progress.record_completion(
+        level_id,
+        history.toggles_count(),
+        stopwatch.0.elapsed().as_millis() as u64,
+    );
+    store.save_or_warn(&progress);
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::prelude::{IntoSystem, System};
+
+    use crate::{
+        data::FaceDirection,
+        headless::HeadlessGame,
+        logic::level::LevelStopwatch,
+        simulation::{Action, TilePosition, TICKS_PER_SECOND},
+        solver::TimedAction,
+        storage::MemoryStorage,
+        GameState,
+    };
+
+    use super::{save_level_completion, PlayerProgress, ProgressStore};
+
+    #[test]
+    fn missing_save_loads_the_default_progress() {
+        let store = ProgressStore(Box::<MemoryStorage>::default());
+        assert_eq!(store.load().unwrap(), PlayerProgress::default());
+    }
+
+    #[test]
+    fn progress_round_trips_through_the_store() {
+        let mut progress = PlayerProgress::default();
+        progress.record_completion("I", 3, 600);
+        progress.record_completion("II", 5, 1200);
+        progress.last_played_level = Some(String::from("III"));
+
+        let mut store = ProgressStore(Box::<MemoryStorage>::default());
+        store.save(&progress).unwrap();
+        assert_eq!(store.load().unwrap(), progress);
+    }
+
+    #[test]
+    fn completion_keeps_the_best_moves_and_time() {
+        let mut progress = PlayerProgress::default();
+        progress.record_completion("I", 3, 600);
+        progress.record_completion("I", 2, 900);
+
+        let record = &progress.completed_levels["I"];
+        assert_eq!((record.best_moves, record.best_time), (2, 600));
+    }
+
+    #[test]
+    fn completion_after_an_undo_keeps_the_undone_move_and_time() {
+        let toggle = |i, j| TimedAction {
+            tick: 0,
+            action: Action::ToggleRod {
+                pillar: 0,
+                face: FaceDirection::East,
+                position: TilePosition { i, j },
+            },
+        };
+        let mut game = HeadlessGame::new("I").unwrap();
+        assert!(game.apply(&toggle(2, 4).action));
+        game.advance_ticks(30);
+        assert!(game.undo());
+        assert_eq!(game.simulation().tick(), 0);
+        assert!(game.play(&[toggle(2, 4), toggle(3, 5)], 30 * 60));
+        game.advance_ticks(1);
+        assert_eq!(game.state(), GameState::Won);
+
+        // Undo rewound the simulation by the 30 ticks played before it, not the stopwatch
+        let simulation_millis = game.simulation().tick() * 1000 / TICKS_PER_SECOND as u64;
+        let level_id = game.level_id();
+        let world = game.world_mut();
+        world.insert_resource(PlayerProgress::default());
+        world.insert_resource(ProgressStore(Box::<MemoryStorage>::default()));
+        let mut system = IntoSystem::into_system(save_level_completion);
+        system.initialize(world);
+        system.run((), world);
+
+        let elapsed = world.resource::<LevelStopwatch>().0.elapsed();
+        let record = &world.resource::<PlayerProgress>().completed_levels[level_id];
+        assert_eq!(record.best_moves, 3);
+        assert_eq!(record.best_time, elapsed.as_millis() as u64);
+        assert!(record.best_time >= simulation_millis + 400);
+    }
+}
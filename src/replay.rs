@@ -0,0 +1,188 @@
+//! Recording of the level attempts, and their playback.
+//!
+//! A [`Replay`] is the campaign level id and the player actions, each with the simulation tick it was applied at. The
+//! simulation being deterministic, applying the same actions at the same ticks of [`CoreSchedule::FixedUpdate`]
+//! reproduces the attempt exactly.
+//!
+//! [`CoreSchedule::FixedUpdate`]: bevy::prelude::CoreSchedule::FixedUpdate
+
+use std::{fmt, io, path::PathBuf};
+
+use bevy::prelude::{info, warn, Assets, Commands, EventReader, Res, ResMut, Resource};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    data::Campaign,
+    logic::{
+        history::LevelHistory,
+        level::{GameLevels, LevelLoaded},
+        rod::RodToggles,
+    },
+    simulation::Action,
+    storage::{platform_storage, FileStorage, Storage},
+};
+
+const LAST_REPLAY_FILE_NAME: &str = "last_replay.ron";
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReplayAction {
+    /// Simulation tick the action was applied at, before the simulation stepped
+    pub tick: u64,
+    pub action: Action,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Replay {
+    /// Campaign level id
+    pub level_id: String,
+    /// By increasing tick
+    pub actions: Vec<ReplayAction>,
+}
+
+impl Replay {
+    /// Actions leading to the current state of the level, undone actions excluded
+    pub fn from_history(level_id: &str, history: &LevelHistory) -> Self {
+        Self {
+            level_id: level_id.to_string(),
+            actions: history
+                .actions()
+                .map(|(tick, action)| ReplayAction {
+                    tick,
+                    action: action.clone(),
+                })
+                .collect(),
+        }
+    }
+
+    pub fn from_ron_bytes(bytes: &[u8]) -> Result<Replay, ron::error::SpannedError> {
+        ron::de::from_bytes(bytes)
+    }
+
+    pub fn to_ron_string(&self) -> Result<String, ron::Error> {
+        ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::new().struct_names(false))
+    }
+}
+
+#[derive(Debug)]
+pub enum ReplayError {
+    Io(io::Error),
+    NotFound,
+    Parse(ron::error::SpannedError),
+    Serialize(ron::Error),
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplayError::Io(error) => write!(f, "{error}"),
+            ReplayError::NotFound => write!(f, "no replay file"),
+            ReplayError::Parse(error) => write!(f, "invalid replay: {error}"),
+            ReplayError::Serialize(error) => write!(f, "failed to serialize the replay: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+/// Where the last attempt is saved, won or lost
+#[derive(Resource)]
+pub struct ReplayStore(pub Box<dyn Storage>);
+
+impl ReplayStore {
+    /// The replay file of the platform, falling back to memory
+    pub fn for_platform() -> Self {
+        Self(platform_storage(LAST_REPLAY_FILE_NAME))
+    }
+
+    pub fn save(&mut self, replay: &Replay) -> Result<(), ReplayError> {
+        let content = replay.to_ron_string().map_err(ReplayError::Serialize)?;
+        self.0.write(content.as_bytes()).map_err(ReplayError::Io)
+    }
+}
+
+/// Replay file given on the command line, or saved by [`ReplayStore`]
+pub fn load_replay_file(path: PathBuf) -> Result<Replay, ReplayError> {
+    let bytes = FileStorage::new(path)
+        .read()
+        .map_err(ReplayError::Io)?
+        .ok_or(ReplayError::NotFound)?;
+    Replay::from_ron_bytes(&bytes).map_err(ReplayError::Parse)
+}
+
+/// Saves the attempt that just ended
+pub fn save_replay(
+    game_levels: Res<GameLevels>,
+    campaigns: Res<Assets<Campaign>>,
+    history: Res<LevelHistory>,
+    mut store: ResMut<ReplayStore>,
+) {
+    let Some(level_id) = game_levels.get_current_level_id(&campaigns) else {
+        return;
+    };
+    match store.save(&Replay::from_history(level_id, &history)) {
+        Ok(()) => info!("Replay of level {level_id} saved"),
+        Err(error) => warn!("Failed to save the replay: {error}"),
+    }
+}
+
+/// Replay being played back. Player actions are disabled while it exists.
+#[derive(Resource)]
+pub struct ReplayPlayback {
+    replay: Replay,
+    /// Index of the next action to apply, `None` until the level of the replay is loaded
+    next_action: Option<usize>,
+}
+
+impl ReplayPlayback {
+    pub fn new(replay: Replay) -> Self {
+        Self {
+            replay,
+            next_action: None,
+        }
+    }
+
+    pub fn level_id(&self) -> &str {
+        &self.replay.level_id
+    }
+}
+
+/// Starts the playback over whenever its level is (re)loaded, and stops it when another level is loaded
+pub fn restart_replay_playback(
+    mut commands: Commands,
+    mut level_loaded_events: EventReader<LevelLoaded>,
+    mut playback: ResMut<ReplayPlayback>,
+) {
+    for LevelLoaded { level_id, .. } in level_loaded_events.iter() {
+        if *level_id == playback.replay.level_id {
+            info!("Playing back the replay of level {level_id}");
+            playback.next_action = Some(0);
+        } else {
+            info!("Level {level_id} loaded, replay playback stopped");
+            commands.remove_resource::<ReplayPlayback>();
+        }
+    }
+}
+
+/// Applies the actions of the replay at their tick. Runs in the fixed schedule, right before the simulation steps.
+pub fn play_replay_actions(mut playback: ResMut<ReplayPlayback>, mut rod_toggles: RodToggles) {
+    let Some(mut next_action) = playback.next_action else {
+        return;
+    };
+    let tick = rod_toggles.tick();
+    while let Some(replay_action) = playback.replay.actions.get(next_action) {
+        if replay_action.tick > tick {
+            break;
+        }
+        if replay_action.tick < tick || !rod_toggles.apply(&replay_action.action) {
+            warn!(
+                "Replay diverged at tick {tick}: {:?} recorded at tick {} could not be applied",
+                replay_action.action, replay_action.tick
+            );
+        }
+        next_action += 1;
+        if next_action == playback.replay.actions.len() {
+            info!("End of the replay actions");
+        }
+    }
+    playback.next_action = Some(next_action);
+}
@@ -0,0 +1,404 @@
+//! Player settings, kept across sessions and applied as soon as they change.
+
+use std::fmt;
+
+use bevy::{
+    prelude::{
+        default, warn, BuildChildren, ButtonBundle, Changed, ChildBuilder, Color, Commands,
+        Component, DespawnRecursiveExt, DetectChanges, Entity, NodeBundle, Query, Ref, Res, ResMut,
+        Resource, TextBundle, Visibility, With,
+    },
+    text::{Text, TextStyle},
+    ui::{
+        AlignItems, BackgroundColor, FlexDirection, FocusPolicy, Interaction, JustifyContent,
+        PositionType, Size, Style, UiRect, Val, ZIndex,
+    },
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    assets::GameAssets,
+    level_select::{BUTTON_COLOR, BUTTON_HOVERED_COLOR},
+    storage::{platform_storage, Storage},
+};
+
+const SETTINGS_FILE_NAME: &str = "settings.ron";
+
+#[derive(Resource, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub mouse_rotate_sensitivity: f32,
+    pub mouse_wheel_zoom_sensitivity: f32,
+    /// Closest distance of the camera to small levels, bigger levels are seen from further away
+    pub min_camera_distance: f32,
+    /// Distance the camera can zoom out, from its closest distance
+    pub camera_zoom_range: f32,
+    pub invert_orbit: bool,
+    pub bloom: bool,
+    pub shadows: bool,
+    pub grass: bool,
+    /// From 0 to 1
+    pub music_volume: f32,
+    /// From 0 to 1
+    pub effects_volume: f32,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            mouse_rotate_sensitivity: 0.2,
+            mouse_wheel_zoom_sensitivity: 0.2,
+            min_camera_distance: 8.,
+            camera_zoom_range: 7.,
+            invert_orbit: false,
+            bloom: true,
+            shadows: true,
+            grass: true,
+            music_volume: 0.5,
+            effects_volume: 0.8,
+        }
+    }
+}
+
+impl Settings {
+    pub fn from_ron_bytes(bytes: &[u8]) -> Result<Settings, ron::error::SpannedError> {
+        ron::de::from_bytes(bytes)
+    }
+
+    pub fn to_ron_string(&self) -> Result<String, ron::Error> {
+        ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::new().struct_names(false))
+    }
+}
+
+/// Storage backend of the [`Settings`] resource
+#[derive(Resource)]
+pub struct SettingsStore(pub Box<dyn Storage>);
+
+impl SettingsStore {
+    /// The settings file of the platform, falling back to memory
+    pub fn for_platform() -> Self {
+        Self(platform_storage(SETTINGS_FILE_NAME))
+    }
+
+    /// Falls back to the default settings when they can't be loaded
+    pub fn load_or_default(&self) -> Settings {
+        let loaded = match self.0.read() {
+            Ok(Some(bytes)) => Settings::from_ron_bytes(&bytes).map_err(|e| e.to_string()),
+            Ok(None) => Ok(Settings::default()),
+            Err(error) => Err(error.to_string()),
+        };
+        loaded.unwrap_or_else(|error| {
+            warn!("Failed to load the settings: {error}");
+            Settings::default()
+        })
+    }
+
+    pub fn save(&mut self, settings: &Settings) {
+        let saved = match settings.to_ron_string() {
+            Ok(content) => self.0.write(content.as_bytes()).map_err(|e| e.to_string()),
+            Err(error) => Err(error.to_string()),
+        };
+        if let Err(error) = saved {
+            warn!("Failed to save the settings: {error}");
+        }
+    }
+}
+
+pub fn save_settings(settings: Res<Settings>, mut store: ResMut<SettingsStore>) {
+    if settings.is_changed() && !settings.is_added() {
+        store.save(&settings);
+    }
+}
+
+/// Setting edited by a row of the settings screen
+#[derive(Clone, Copy, Debug)]
+pub enum SettingField {
+    RotateSensitivity,
+    ZoomSensitivity,
+    MinCameraDistance,
+    CameraZoomRange,
+    InvertOrbit,
+    Bloom,
+    Shadows,
+    Grass,
+    MusicVolume,
+    EffectsVolume,
+}
+
+impl SettingField {
+    /// Rows of the settings screen, without the settings of the features the game was built without
+    const ALL: &'static [SettingField] = &[
+        SettingField::RotateSensitivity,
+        SettingField::ZoomSensitivity,
+        SettingField::MinCameraDistance,
+        SettingField::CameraZoomRange,
+        SettingField::InvertOrbit,
+        #[cfg(feature = "fancy-graphics")]
+        SettingField::Bloom,
+        SettingField::Shadows,
+        #[cfg(feature = "grass")]
+        SettingField::Grass,
+        SettingField::MusicVolume,
+        SettingField::EffectsVolume,
+    ];
+
+    fn label(&self) -> &'static str {
+        match self {
+            SettingField::RotateSensitivity => "Rotation sensitivity",
+            SettingField::ZoomSensitivity => "Zoom sensitivity",
+            SettingField::MinCameraDistance => "Closest zoom",
+            SettingField::CameraZoomRange => "Zoom range",
+            SettingField::InvertOrbit => "Invert rotation",
+            SettingField::Bloom => "Bloom",
+            SettingField::Shadows => "Shadows",
+            SettingField::Grass => "Grass",
+            SettingField::MusicVolume => "Music volume",
+            SettingField::EffectsVolume => "Effects volume",
+        }
+    }
+
+    fn value(&self, settings: &Settings) -> SettingValue {
+        match self {
+            SettingField::RotateSensitivity => {
+                SettingValue::Number(settings.mouse_rotate_sensitivity)
+            }
+            SettingField::ZoomSensitivity => {
+                SettingValue::Number(settings.mouse_wheel_zoom_sensitivity)
+            }
+            SettingField::MinCameraDistance => SettingValue::Number(settings.min_camera_distance),
+            SettingField::CameraZoomRange => SettingValue::Number(settings.camera_zoom_range),
+            SettingField::InvertOrbit => SettingValue::Toggle(settings.invert_orbit),
+            SettingField::Bloom => SettingValue::Toggle(settings.bloom),
+            SettingField::Shadows => SettingValue::Toggle(settings.shadows),
+            SettingField::Grass => SettingValue::Toggle(settings.grass),
+            SettingField::MusicVolume => SettingValue::Percent(settings.music_volume),
+            SettingField::EffectsVolume => SettingValue::Percent(settings.effects_volume),
+        }
+    }
+
+    /// Toggles the boolean settings whatever the direction
+    fn step(&self, settings: &mut Settings, direction: f32) {
+        let (value, step, min, max) = match self {
+            SettingField::RotateSensitivity => {
+                (&mut settings.mouse_rotate_sensitivity, 0.05, 0.05, 1.)
+            }
+            SettingField::ZoomSensitivity => {
+                (&mut settings.mouse_wheel_zoom_sensitivity, 0.05, 0.05, 0.5)
+            }
+            SettingField::MinCameraDistance => (&mut settings.min_camera_distance, 1., 4., 20.),
+            SettingField::CameraZoomRange => (&mut settings.camera_zoom_range, 1., 0., 20.),
+            SettingField::MusicVolume => (&mut settings.music_volume, 0.1, 0., 1.),
+            SettingField::EffectsVolume => (&mut settings.effects_volume, 0.1, 0., 1.),
+            SettingField::InvertOrbit
+            | SettingField::Bloom
+            | SettingField::Shadows
+            | SettingField::Grass => {
+                let toggle = match self {
+                    SettingField::InvertOrbit => &mut settings.invert_orbit,
+                    SettingField::Bloom => &mut settings.bloom,
+                    SettingField::Shadows => &mut settings.shadows,
+                    _ => &mut settings.grass,
+                };
+                *toggle = !*toggle;
+                return;
+            }
+        };
+        // Rounded to the step, so that repeated steps do not accumulate float errors
+        *value = (((*value + direction * step) / step).round() * step).clamp(min, max);
+    }
+}
+
+enum SettingValue {
+    Number(f32),
+    Percent(f32),
+    Toggle(bool),
+}
+
+impl fmt::Display for SettingValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SettingValue::Number(value) => write!(f, "{value:.2}"),
+            SettingValue::Percent(value) => write!(f, "{:.0}%", value * 100.),
+            SettingValue::Toggle(true) => write!(f, "On"),
+            SettingValue::Toggle(false) => write!(f, "Off"),
+        }
+    }
+}
+
+/// Root of the settings overlay
+#[derive(Component, Clone, Debug)]
+pub struct SettingsUI;
+
+#[derive(Component, Clone, Debug)]
+pub enum SettingsButton {
+    Step { field: SettingField, direction: f32 },
+    Close,
+}
+
+#[derive(Component, Clone, Debug)]
+pub struct SettingValueText(SettingField);
+
+pub fn setup_settings_ui(mut commands: Commands) {
+    commands.spawn((
+        NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                position_type: PositionType::Absolute,
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+            background_color: Color::rgba(0., 0., 0., 0.6).into(),
+            visibility: Visibility::Hidden,
+            z_index: ZIndex::Global(10),
+            ..default()
+        },
+        // Blocks the picking of the level behind the overlay
+        Interaction::default(),
+        FocusPolicy::Block,
+        SettingsUI,
+    ));
+}
+
+fn spawn_button(parent: &mut ChildBuilder, label: &str, style: &TextStyle, button: SettingsButton) {
+    parent
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    margin: UiRect::horizontal(Val::Px(5.0)),
+                    padding: UiRect::horizontal(Val::Px(10.0)),
+                    ..default()
+                },
+                background_color: BUTTON_COLOR.into(),
+                ..default()
+            },
+            button,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(label, style.clone()));
+        });
+}
+
+fn spawn_settings_rows(parent: &mut ChildBuilder, assets: &GameAssets, settings: &Settings) {
+    let title_style = TextStyle {
+        font: assets.font.clone(),
+        font_size: 45.0,
+        color: Color::WHITE,
+    };
+    let row_style = TextStyle {
+        font_size: 25.0,
+        ..title_style.clone()
+    };
+
+    parent.spawn(TextBundle::from_section("Settings", title_style));
+    for &field in SettingField::ALL {
+        parent
+            .spawn(NodeBundle {
+                style: Style {
+                    margin: UiRect::all(Val::Px(3.0)),
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                ..default()
+            })
+            .with_children(|row| {
+                row.spawn(
+                    TextBundle::from_section(field.label(), row_style.clone()).with_style(Style {
+                        size: Size::width(Val::Px(220.0)),
+                        ..default()
+                    }),
+                );
+                spawn_button(
+                    row,
+                    "<",
+                    &row_style,
+                    SettingsButton::Step {
+                        field,
+                        direction: -1.,
+                    },
+                );
+                row.spawn((
+                    TextBundle::from_section(field.value(settings).to_string(), row_style.clone())
+                        .with_style(Style {
+                            size: Size::width(Val::Px(70.0)),
+                            justify_content: JustifyContent::Center,
+                            ..default()
+                        }),
+                    SettingValueText(field),
+                ));
+                spawn_button(
+                    row,
+                    ">",
+                    &row_style,
+                    SettingsButton::Step {
+                        field,
+                        direction: 1.,
+                    },
+                );
+            });
+    }
+    parent
+        .spawn(NodeBundle {
+            style: Style {
+                margin: UiRect::top(Val::Px(15.0)),
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|parent| {
+            spawn_button(parent, "Back", &row_style, SettingsButton::Close);
+        });
+}
+
+/// Builds the settings screen when it is opened
+pub fn populate_settings_ui(
+    mut commands: Commands,
+    settings_ui: Query<(Entity, Ref<Visibility>), With<SettingsUI>>,
+    assets: Res<GameAssets>,
+    settings: Res<Settings>,
+) {
+    let Ok((entity, visibility)) = settings_ui.get_single() else {
+        return;
+    };
+    if !visibility.is_changed() {
+        return;
+    }
+    commands.entity(entity).despawn_descendants();
+    if *visibility != Visibility::Hidden {
+        commands.entity(entity).with_children(|parent| {
+            spawn_settings_rows(parent, &assets, &settings);
+        });
+    }
+}
+
+pub fn handle_settings_buttons(
+    mut buttons: Query<(&Interaction, &mut BackgroundColor, &SettingsButton), Changed<Interaction>>,
+    mut settings_ui: Query<&mut Visibility, With<SettingsUI>>,
+    mut settings: ResMut<Settings>,
+) {
+    for (interaction, mut background_color, button) in buttons.iter_mut() {
+        match interaction {
+            Interaction::Clicked => match button {
+                SettingsButton::Step { field, direction } => {
+                    field.step(&mut settings, *direction);
+                }
+                SettingsButton::Close => *settings_ui.single_mut() = Visibility::Hidden,
+            },
+            Interaction::Hovered => *background_color = BUTTON_HOVERED_COLOR.into(),
+            Interaction::None => *background_color = BUTTON_COLOR.into(),
+        }
+    }
+}
+
+pub fn update_settings_ui(
+    settings: Res<Settings>,
+    mut value_texts: Query<(&mut Text, &SettingValueText)>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+    for (mut text, SettingValueText(field)) in value_texts.iter_mut() {
+        text.sections.first_mut().unwrap().value = field.value(&settings).to_string();
+    }
+}
@@ -0,0 +1,1010 @@
+//! Rules of the puzzle, independent from Bevy.
+//!
+//! A [`Simulation`] is built from a [`LevelData`] and advanced one fixed tick at a time with [`Simulation::step`].
+//! Player inputs are applied with [`Simulation::apply`]. The game systems only mirror its state visually.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    assets::{TILE_SIZE, VISIBLE_ROD_LENGTH},
+    data::{FaceDirection, FaceSize, LevelData, PillarData, TileDataType},
+};
+
+/// Rate at which [`Simulation::step`] is called by the game
+pub const TICKS_PER_SECOND: u32 = 60;
+/// Duration of a climber hop from one rod to the next
+pub const CLIMBER_MOVE_TICKS: u32 = 49;
+/// A toggled movable rod can't be toggled again before this delay
+pub const ROD_TOGGLE_COOLDOWN_TICKS: u32 = TICKS_PER_SECOND;
+/// A falling climber goes down one tile every `FALL_STEPS_PER_TILE` ticks
+pub const FALL_STEPS_PER_TILE: i32 = 10;
+/// A falling climber dies when its height reaches the ground
+pub const DEATH_HEIGHT: i32 = -3 * FALL_STEPS_PER_TILE / 2;
+/// Facing faces of two pillars closer than this would have their rods and climbers stick into each other.
+/// Climbers can jump between facing faces separated by exactly this gap.
+pub const MIN_PILLAR_GAP: f32 = 2. * VISIBLE_ROD_LENGTH;
+/// Tolerance on the distances between pillars, which are given as floats in the level data
+pub const PILLAR_GAP_TOLERANCE: f32 = TILE_SIZE / 10.;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct TilePosition {
+    pub i: u16,
+    pub j: u16,
+}
+
+/// The `bool` is `true` when a climber occupies the rod
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TileType {
+    Void,
+    StaticRod(bool),
+    MovableRod(bool),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct FaceId {
+    pub pillar: usize,
+    pub direction: FaceDirection,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct FaceTile {
+    pub face: FaceId,
+    pub position: TilePosition,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct PylonId {
+    pub pillar: usize,
+    pub face: FaceDirection,
+    /// Index of the pylon on its face
+    pub index: usize,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ClimberState {
+    Waiting {
+        on_tile: FaceTile,
+    },
+    Moving {
+        to_tile: FaceTile,
+        remaining_ticks: u32,
+    },
+    Falling {
+        face: FaceId,
+        i: u16,
+        /// In fall steps, `j * FALL_STEPS_PER_TILE` is the height of row `j`
+        height: i32,
+    },
+    Saved {
+        pylon: PylonId,
+    },
+    Dead,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Climber {
+    pub state: ClimberState,
+    /// Pillar of the last tile the climber moved to
+    pub current_pillar: usize,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct MovableRod {
+    pub pillar: usize,
+    /// Face on which the rod is currently sticking out
+    pub face: FaceDirection,
+    pub position: TilePosition,
+    pub cooldown_ticks: u32,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct Face {
+    /// Indexed by `[i][j]`
+    tiles: Vec<Vec<TileType>>,
+    /// `true` when powered
+    pylons: Vec<bool>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct Pillar {
+    size: FaceSize,
+    /// Indexed by [`FaceDirection::index`]
+    faces: [Face; 4],
+    /// Indexed by [`FaceDirection::index`]
+    bridges: [Vec<Bridge>; 4],
+}
+
+/// Face of another pillar facing a face of this pillar, across a gap
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct Bridge {
+    pillar: usize,
+    /// Added to a column of this face, gives the column of the facing face in front of it
+    column_offset: i32,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Action {
+    /// Slides the movable rod currently sticking out of `face` at `position` to the opposite face
+    ToggleRod {
+        pillar: usize,
+        face: FaceDirection,
+        position: TilePosition,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ActionError {
+    NoMovableRod,
+    /// The rod is still sliding from its previous toggle
+    RodMoving,
+}
+
+impl fmt::Display for ActionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ActionError::NoMovableRod => write!(f, "there is no movable rod at this position"),
+            ActionError::RodMoving => write!(f, "the rod is still moving"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SimulationEvent {
+    RodToggled {
+        pillar: usize,
+        from: FaceDirection,
+        to: FaceDirection,
+        position: TilePosition,
+    },
+    ClimberMoved {
+        climber: usize,
+        from: FaceTile,
+        to: FaceTile,
+    },
+    ClimberStartedFalling {
+        climber: usize,
+        from: FaceTile,
+    },
+    ClimberLanded {
+        climber: usize,
+        on: FaceTile,
+    },
+    ClimberSaved {
+        climber: usize,
+        pylon: PylonId,
+    },
+    ClimberDied {
+        climber: usize,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SimulationStatus {
+    Playing,
+    Won,
+    Lost,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Simulation {
+    tick: u64,
+    pillars: Vec<Pillar>,
+    climbers: Vec<Climber>,
+    rods: Vec<MovableRod>,
+}
+
+/// See [`Simulation::state_key`]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct StateKey {
+    pillars: Vec<Pillar>,
+    climbers: Vec<Climber>,
+    rods: Vec<MovableRod>,
+}
+
+impl Simulation {
+    /// Climbers, rods and pylons are ordered by pillar, then by face in [`FaceDirection::ALL`] order, then as in the data.
+    ///
+    /// `level_data` is expected to be valid, see [`LevelData::validate`].
+    pub fn new(level_data: &LevelData) -> Self {
+        let mut pillars = Vec::new();
+        let mut climbers = Vec::new();
+        let mut rods = Vec::new();
+
+        for (pillar_idx, pillar_data) in level_data.pillars.iter().enumerate() {
+            let size = FaceSize {
+                w: pillar_data.w,
+                h: pillar_data.h,
+            };
+            let empty_face = Face {
+                tiles: vec![vec![TileType::Void; size.h as usize]; size.w as usize],
+                pylons: vec![],
+            };
+            let mut pillar = Pillar {
+                size,
+                faces: [
+                    empty_face.clone(),
+                    empty_face.clone(),
+                    empty_face.clone(),
+                    empty_face,
+                ],
+                bridges: Default::default(),
+            };
+
+            for direction in FaceDirection::ALL {
+                let Some(face_data) = pillar_data.faces.get(&direction) else {
+                    continue;
+                };
+                let face = &mut pillar.faces[direction.index()];
+                for tile in face_data.tiles.iter() {
+                    face.tiles[tile.i as usize][tile.j as usize] = match tile.kind {
+                        TileDataType::StaticRod => TileType::StaticRod(false),
+                        TileDataType::MovableRod => {
+                            rods.push(MovableRod {
+                                pillar: pillar_idx,
+                                face: direction.clone(),
+                                position: TilePosition {
+                                    i: tile.i,
+                                    j: tile.j,
+                                },
+                                cooldown_ticks: 0,
+                            });
+                            TileType::MovableRod(false)
+                        }
+                    };
+                }
+                for climber_data in face_data.climbers.iter() {
+                    let on_tile = FaceTile {
+                        face: FaceId {
+                            pillar: pillar_idx,
+                            direction: direction.clone(),
+                        },
+                        position: TilePosition {
+                            i: climber_data.tile_i,
+                            j: climber_data.tile_j,
+                        },
+                    };
+                    set_occupied(
+                        &mut face.tiles[on_tile.position.i as usize][on_tile.position.j as usize],
+                        true,
+                    );
+                    climbers.push(Climber {
+                        state: ClimberState::Waiting { on_tile },
+                        current_pillar: pillar_idx,
+                    });
+                    face.pylons.push(false);
+                }
+            }
+            pillars.push(pillar);
+        }
+        link_bridges(&level_data.pillars, &mut pillars);
+
+        Self {
+            tick: 0,
+            pillars,
+            climbers,
+            rods,
+        }
+    }
+
+    /// Number of [`Simulation::step`] calls since the start of the level
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    pub fn climbers(&self) -> &[Climber] {
+        &self.climbers
+    }
+
+    pub fn rods(&self) -> &[MovableRod] {
+        &self.rods
+    }
+
+    pub fn face_size(&self, pillar: usize) -> FaceSize {
+        self.pillars[pillar].size
+    }
+
+    /// Returns `None` when out of the face
+    pub fn get_tile(&self, face: &FaceId, i: i32, j: i32) -> Option<TileType> {
+        let size = self.pillars.get(face.pillar)?.size;
+        if i < 0 || j < 0 || i >= size.w as i32 || j >= size.h as i32 {
+            return None;
+        }
+        Some(self.pillars[face.pillar].faces[face.direction.index()].tiles[i as usize][j as usize])
+    }
+
+    pub fn has_ground_on_tile(&self, face: &FaceId, i: i32, j: i32) -> bool {
+        !matches!(self.get_tile(face, i, j), None | Some(TileType::Void))
+    }
+
+    pub fn has_free_ground_on_tile(&self, face: &FaceId, i: i32, j: i32) -> bool {
+        matches!(
+            self.get_tile(face, i, j),
+            Some(TileType::StaticRod(false) | TileType::MovableRod(false))
+        )
+    }
+
+    pub fn is_pylon_powered(&self, pylon: &PylonId) -> bool {
+        self.pillars[pylon.pillar].faces[pylon.face.index()].pylons[pylon.index]
+    }
+
+    pub fn pylons_count(&self) -> usize {
+        self.pillars
+            .iter()
+            .flat_map(|pillar| pillar.faces.iter())
+            .map(|face| face.pylons.len())
+            .sum()
+    }
+
+    pub fn powered_pylons_count(&self) -> usize {
+        self.pillars
+            .iter()
+            .flat_map(|pillar| pillar.faces.iter())
+            .flat_map(|face| face.pylons.iter())
+            .filter(|&&powered| powered)
+            .count()
+    }
+
+    pub fn status(&self) -> SimulationStatus {
+        if self
+            .climbers
+            .iter()
+            .any(|climber| climber.state == ClimberState::Dead)
+        {
+            SimulationStatus::Lost
+        } else if self.powered_pylons_count() >= self.pylons_count() {
+            SimulationStatus::Won
+        } else {
+            SimulationStatus::Playing
+        }
+    }
+
+    /// Whole state except the current tick. Two simulations with equal keys evolve identically.
+    pub fn state_key(&self) -> StateKey {
+        StateKey {
+            pillars: self.pillars.clone(),
+            climbers: self.climbers.clone(),
+            rods: self.rods.clone(),
+        }
+    }
+
+    /// Returns `true` if the rod exists and can be toggled now
+    pub fn can_toggle_rod(
+        &self,
+        pillar: usize,
+        face: &FaceDirection,
+        position: TilePosition,
+    ) -> bool {
+        self.find_rod(pillar, face, position)
+            .is_some_and(|rod_idx| self.rods[rod_idx].cooldown_ticks == 0)
+    }
+
+    fn find_rod(
+        &self,
+        pillar: usize,
+        face: &FaceDirection,
+        position: TilePosition,
+    ) -> Option<usize> {
+        self.rods
+            .iter()
+            .position(|rod| rod.pillar == pillar && rod.face == *face && rod.position == position)
+    }
+
+    pub fn apply(&mut self, action: &Action) -> Result<Vec<SimulationEvent>, ActionError> {
+        match action {
+            Action::ToggleRod {
+                pillar,
+                face,
+                position,
+            } => {
+                let rod_idx = self
+                    .find_rod(*pillar, face, *position)
+                    .ok_or(ActionError::NoMovableRod)?;
+                if self.rods[rod_idx].cooldown_ticks > 0 {
+                    return Err(ActionError::RodMoving);
+                }
+
+                // A climber standing on the rod loses its ground immediately
+                let opposite_face = face.get_opposite();
+                let faces = &mut self.pillars[*pillar].faces;
+                faces[face.index()].tiles[position.i as usize][position.j as usize] =
+                    TileType::Void;
+                faces[opposite_face.index()].tiles[position.i as usize][position.j as usize] =
+                    TileType::MovableRod(false);
+
+                let rod = &mut self.rods[rod_idx];
+                rod.face = opposite_face.clone();
+                rod.cooldown_ticks = ROD_TOGGLE_COOLDOWN_TICKS;
+
+                Ok(vec![SimulationEvent::RodToggled {
+                    pillar: *pillar,
+                    from: face.clone(),
+                    to: opposite_face,
+                    position: *position,
+                }])
+            }
+        }
+    }
+
+    /// Advances the simulation by one tick
+    pub fn step(&mut self) -> Vec<SimulationEvent> {
+        self.tick += 1;
+        for rod in self.rods.iter_mut() {
+            rod.cooldown_ticks = rod.cooldown_ticks.saturating_sub(1);
+        }
+
+        let mut events = Vec::new();
+        for climber_idx in 0..self.climbers.len() {
+            let next_state = match &self.climbers[climber_idx].state {
+                ClimberState::Waiting { on_tile } => {
+                    let (i, j) = (on_tile.position.i as i32, on_tile.position.j as i32);
+                    // If climber doesn't have a rod beneath him anymore : falling
+                    if !self.has_ground_on_tile(&on_tile.face, i, j) {
+                        events.push(SimulationEvent::ClimberStartedFalling {
+                            climber: climber_idx,
+                            from: on_tile.clone(),
+                        });
+                        Some(ClimberState::Falling {
+                            face: on_tile.face.clone(),
+                            i: on_tile.position.i,
+                            height: j * FALL_STEPS_PER_TILE,
+                        })
+                    } else if let Some(next_tile) = self.get_next_free_tile_with_ground(on_tile) {
+                        let from = on_tile.clone();
+                        self.set_tile_occupied(&from, false);
+                        self.set_tile_occupied(&next_tile, true);
+                        self.climbers[climber_idx].current_pillar = next_tile.face.pillar;
+                        events.push(SimulationEvent::ClimberMoved {
+                            climber: climber_idx,
+                            from,
+                            to: next_tile.clone(),
+                        });
+                        Some(ClimberState::Moving {
+                            to_tile: next_tile,
+                            remaining_ticks: CLIMBER_MOVE_TICKS,
+                        })
+                    } else {
+                        None
+                    }
+                }
+                ClimberState::Moving {
+                    to_tile,
+                    remaining_ticks,
+                } => {
+                    if *remaining_ticks > 1 {
+                        Some(ClimberState::Moving {
+                            to_tile: to_tile.clone(),
+                            remaining_ticks: remaining_ticks - 1,
+                        })
+                    } else if to_tile.position.j >= self.pillars[to_tile.face.pillar].size.h - 1 {
+                        let to_tile = to_tile.clone();
+                        self.set_tile_occupied(&to_tile, false);
+                        let pylon = self
+                            .pop_pylon(&to_tile.face)
+                            .expect("There should be as many pylons as climbers");
+                        self.pillars[pylon.pillar].faces[pylon.face.index()].pylons[pylon.index] =
+                            true;
+                        events.push(SimulationEvent::ClimberSaved {
+                            climber: climber_idx,
+                            pylon: pylon.clone(),
+                        });
+                        Some(ClimberState::Saved { pylon })
+                    } else {
+                        Some(ClimberState::Waiting {
+                            on_tile: to_tile.clone(),
+                        })
+                    }
+                }
+                ClimberState::Falling { face, i, height } => {
+                    // Round to the nearest row
+                    let row = (height + FALL_STEPS_PER_TILE / 2).div_euclid(FALL_STEPS_PER_TILE);
+                    // If a rod is reached, even one occupied by another climber : waiting
+                    if self.has_ground_on_tile(face, *i as i32, row) {
+                        let landed_on = FaceTile {
+                            face: face.clone(),
+                            position: TilePosition {
+                                i: *i,
+                                j: row as u16,
+                            },
+                        };
+                        self.set_tile_occupied(&landed_on, true);
+                        events.push(SimulationEvent::ClimberLanded {
+                            climber: climber_idx,
+                            on: landed_on.clone(),
+                        });
+                        Some(ClimberState::Waiting { on_tile: landed_on })
+                    } else if height - 1 <= DEATH_HEIGHT {
+                        events.push(SimulationEvent::ClimberDied {
+                            climber: climber_idx,
+                        });
+                        Some(ClimberState::Dead)
+                    } else {
+                        Some(ClimberState::Falling {
+                            face: face.clone(),
+                            i: *i,
+                            height: height - 1,
+                        })
+                    }
+                }
+                ClimberState::Saved { .. } | ClimberState::Dead => None,
+            };
+            if let Some(state) = next_state {
+                self.climbers[climber_idx].state = state;
+            }
+        }
+        events
+    }
+
+    fn get_next_free_tile_with_ground(&self, tile: &FaceTile) -> Option<FaceTile> {
+        let (i, j) = (tile.position.i as i32, tile.position.j as i32);
+        [i + 1, i - 1]
+            .into_iter()
+            .flat_map(|next_i| {
+                self.get_column_across_edges(&tile.face, next_i)
+                    .into_iter()
+                    .chain(self.get_column_around_corner(&tile.face, next_i))
+            })
+            .find(|(face, next_i)| self.has_free_ground_on_tile(face, *next_i as i32, j + 1))
+            .map(|(face, next_i)| FaceTile {
+                face,
+                position: TilePosition {
+                    i: next_i,
+                    j: tile.position.j + 1,
+                },
+            })
+    }
+
+    /// Face and column reached when going to column `i` of `face`. Out of the face bounds, this is the column in front
+    /// of the edge column on the facing face of a bridged pillar, if any.
+    fn get_column_across_edges(&self, face: &FaceId, i: i32) -> Option<(FaceId, u16)> {
+        let pillar = &self.pillars[face.pillar];
+        let last_column = pillar.size.w as i32 - 1;
+        if (0..=last_column).contains(&i) {
+            return Some((face.clone(), i as u16));
+        }
+        let edge_column = i.clamp(0, last_column);
+        pillar.bridges[face.direction.index()]
+            .iter()
+            .find_map(|bridge| {
+                let column = edge_column + bridge.column_offset;
+                (0..self.pillars[bridge.pillar].size.w as i32)
+                    .contains(&column)
+                    .then(|| {
+                        (
+                            FaceId {
+                                pillar: bridge.pillar,
+                                direction: face.direction.get_opposite(),
+                            },
+                            column as u16,
+                        )
+                    })
+            })
+    }
+
+    fn set_tile_occupied(&mut self, tile: &FaceTile, occupied: bool) {
+        let face = &mut self.pillars[tile.face.pillar].faces[tile.face.direction.index()];
+        set_occupied(
+            &mut face.tiles[tile.position.i as usize][tile.position.j as usize],
+            occupied,
+        );
+    }
+
+    /// Face and column reached when going to column `i` of `face`, out of the face bounds, around the pillar corner
+    fn get_column_around_corner(&self, face: &FaceId, i: i32) -> Option<(FaceId, u16)> {
+        let last_column = self.pillars[face.pillar].size.w - 1;
+        let past_last_column = i > last_column as i32;
+        if i >= 0 && !past_last_column {
+            return None;
+        }
+        // Columns go towards +z on West/East faces and towards +x on North/South faces
+        let (direction, column) = match (&face.direction, past_last_column) {
+            (FaceDirection::West, false) => (FaceDirection::South, 0),
+            (FaceDirection::West, true) => (FaceDirection::North, 0),
+            (FaceDirection::East, false) => (FaceDirection::South, last_column),
+            (FaceDirection::East, true) => (FaceDirection::North, last_column),
+            (FaceDirection::North, false) => (FaceDirection::West, last_column),
+            (FaceDirection::North, true) => (FaceDirection::East, last_column),
+            (FaceDirection::South, false) => (FaceDirection::West, 0),
+            (FaceDirection::South, true) => (FaceDirection::East, 0),
+        };
+        Some((
+            FaceId {
+                pillar: face.pillar,
+                direction,
+            },
+            column,
+        ))
+    }
+
+    /// Pylon powered by a climber reaching the top of `face`: a pylon of the same face if any, else the first available
+    /// one of the same pillar, else of the other pillars
+    fn pop_pylon(&self, face: &FaceId) -> Option<PylonId> {
+        let other_pillars = (0..self.pillars.len()).filter(|&pillar| pillar != face.pillar);
+        std::iter::once(face.pillar)
+            .chain(other_pillars)
+            .find_map(|pillar_idx| {
+                let pillar = &self.pillars[pillar_idx];
+                [
+                    face.direction.clone(),
+                    FaceDirection::East,
+                    FaceDirection::West,
+                    FaceDirection::South,
+                    FaceDirection::North,
+                ]
+                .into_iter()
+                .find_map(|direction| {
+                    pillar.faces[direction.index()]
+                        .pylons
+                        .iter()
+                        .rposition(|&powered| !powered)
+                        .map(|index| PylonId {
+                            pillar: pillar_idx,
+                            face: direction,
+                            index,
+                        })
+                })
+            })
+    }
+}
+
+/// Links the facing faces of `pillars`, such as the East face of a pillar and the West face of a pillar east of it,
+/// when they are [`MIN_PILLAR_GAP`] apart and their columns line up
+fn link_bridges(pillars_data: &[PillarData], pillars: &mut [Pillar]) {
+    // (plane, first column start) of a face, columns going towards the positive side of their axis
+    let get_face_extent = |pillar: &PillarData, direction: &FaceDirection| {
+        let half_width = pillar.w as f32 * TILE_SIZE / 2.;
+        match direction {
+            FaceDirection::West => (pillar.x - half_width, pillar.z - half_width),
+            FaceDirection::East => (pillar.x + half_width, pillar.z - half_width),
+            FaceDirection::North => (pillar.z + half_width, pillar.x - half_width),
+            FaceDirection::South => (pillar.z - half_width, pillar.x - half_width),
+        }
+    };
+    for (from_idx, from) in pillars_data.iter().enumerate() {
+        for (to_idx, to) in pillars_data.iter().enumerate() {
+            if from_idx == to_idx {
+                continue;
+            }
+            for direction in FaceDirection::ALL {
+                let (from_plane, from_start) = get_face_extent(from, &direction);
+                let (to_plane, to_start) = get_face_extent(to, &direction.get_opposite());
+                let outwards = match direction {
+                    FaceDirection::East | FaceDirection::North => 1.,
+                    FaceDirection::West | FaceDirection::South => -1.,
+                };
+                let gap = (to_plane - from_plane) * outwards;
+                let column_offset = (from_start - to_start) / TILE_SIZE;
+                if (gap - MIN_PILLAR_GAP).abs() <= PILLAR_GAP_TOLERANCE
+                    && (column_offset - column_offset.round()).abs() < 0.1
+                {
+                    pillars[from_idx].bridges[direction.index()].push(Bridge {
+                        pillar: to_idx,
+                        column_offset: column_offset.round() as i32,
+                    });
+                }
+            }
+        }
+    }
+}
+
+fn set_occupied(tile: &mut TileType, occupied: bool) {
+    *tile = match tile {
+        TileType::Void => TileType::Void,
+        TileType::StaticRod(_) => TileType::StaticRod(occupied),
+        TileType::MovableRod(_) => TileType::MovableRod(occupied),
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        assets::TILE_SIZE,
+        data::{FaceDirection, LevelData, TileDataType},
+    };
+
+    use super::{
+        Action, ActionError, ClimberState, FaceId, FaceTile, PylonId, Simulation, SimulationEvent,
+        SimulationStatus, TilePosition, CLIMBER_MOVE_TICKS, MIN_PILLAR_GAP,
+        ROD_TOGGLE_COOLDOWN_TICKS,
+    };
+
+    /// Gives up on climbers still falling after this many ticks
+    const MAX_FALL_TICKS: u32 = 100;
+
+    /// A single `w`x`h` pillar, with every tile and climber on its West face
+    fn level(
+        w: u16,
+        h: u16,
+        tiles: &[(u16, u16, TileDataType)],
+        climbers: &[(u16, u16)],
+    ) -> LevelData {
+        LevelData::test_level()
+            .with_pillar(0., 0., w, h)
+            .with_tiles(FaceDirection::West, tiles)
+            .with_climbers(FaceDirection::West, climbers)
+    }
+
+    fn west_tile(i: u16, j: u16) -> FaceTile {
+        FaceTile {
+            face: FaceId {
+                pillar: 0,
+                direction: FaceDirection::West,
+            },
+            position: TilePosition { i, j },
+        }
+    }
+
+    fn toggle_west_rod(i: u16, j: u16) -> Action {
+        Action::ToggleRod {
+            pillar: 0,
+            face: FaceDirection::West,
+            position: TilePosition { i, j },
+        }
+    }
+
+    fn step_n(simulation: &mut Simulation, ticks: u32) -> Vec<SimulationEvent> {
+        (0..ticks).flat_map(|_| simulation.step()).collect()
+    }
+
+    /// Steps until the climber is no longer falling
+    fn step_while_falling(simulation: &mut Simulation, climber: usize) -> Vec<SimulationEvent> {
+        let mut events = simulation.step();
+        for _ in 0..MAX_FALL_TICKS {
+            if !matches!(
+                simulation.climbers()[climber].state,
+                ClimberState::Falling { .. }
+            ) {
+                return events;
+            }
+            events.extend(simulation.step());
+        }
+        panic!("Climber {climber} still falling after {MAX_FALL_TICKS} ticks");
+    }
+
+    #[test]
+    fn climber_moves_diagonally_up_to_the_next_rod() {
+        use TileDataType::*;
+        let mut simulation = Simulation::new(&level(
+            3,
+            3,
+            &[(0, 0, StaticRod), (1, 1, StaticRod)],
+            &[(0, 0)],
+        ));
+        assert_eq!(
+            simulation.step(),
+            vec![SimulationEvent::ClimberMoved {
+                climber: 0,
+                from: west_tile(0, 0),
+                to: west_tile(1, 1),
+            }]
+        );
+        assert_eq!(
+            simulation.climbers()[0].state,
+            ClimberState::Moving {
+                to_tile: west_tile(1, 1),
+                remaining_ticks: CLIMBER_MOVE_TICKS,
+            }
+        );
+        step_n(&mut simulation, CLIMBER_MOVE_TICKS);
+        assert_eq!(
+            simulation.climbers()[0].state,
+            ClimberState::Waiting {
+                on_tile: west_tile(1, 1)
+            }
+        );
+    }
+
+    #[test]
+    fn climber_waits_without_rod_to_move_to() {
+        let mut simulation =
+            Simulation::new(&level(3, 3, &[(0, 0, TileDataType::StaticRod)], &[(0, 0)]));
+        assert!(step_n(&mut simulation, 100).is_empty());
+        assert_eq!(
+            simulation.climbers()[0].state,
+            ClimberState::Waiting {
+                on_tile: west_tile(0, 0)
+            }
+        );
+        assert_eq!(simulation.status(), SimulationStatus::Playing);
+    }
+
+    #[test]
+    fn climber_waits_for_an_occupied_rod() {
+        use TileDataType::*;
+        let mut simulation = Simulation::new(&level(
+            3,
+            3,
+            &[(0, 0, StaticRod), (1, 1, StaticRod)],
+            &[(0, 0), (1, 1)],
+        ));
+        assert!(simulation.step().is_empty());
+        assert_eq!(
+            simulation.climbers()[0].state,
+            ClimberState::Waiting {
+                on_tile: west_tile(0, 0)
+            }
+        );
+    }
+
+    #[test]
+    fn climber_reaching_the_top_powers_a_pylon() {
+        use TileDataType::*;
+        let mut simulation = Simulation::new(&level(
+            2,
+            2,
+            &[(0, 0, StaticRod), (1, 1, StaticRod)],
+            &[(0, 0)],
+        ));
+        let pylon = PylonId {
+            pillar: 0,
+            face: FaceDirection::West,
+            index: 0,
+        };
+        assert!(!simulation.is_pylon_powered(&pylon));
+        let events = step_n(&mut simulation, 1 + CLIMBER_MOVE_TICKS);
+        assert_eq!(
+            events.last(),
+            Some(&SimulationEvent::ClimberSaved {
+                climber: 0,
+                pylon: pylon.clone()
+            })
+        );
+        assert!(simulation.is_pylon_powered(&pylon));
+        assert_eq!(simulation.powered_pylons_count(), 1);
+        assert_eq!(simulation.status(), SimulationStatus::Won);
+    }
+
+    #[test]
+    fn climber_falls_when_its_rod_is_toggled_and_lands_on_a_rod_below() {
+        use TileDataType::*;
+        let mut simulation = Simulation::new(&level(
+            1,
+            4,
+            &[(0, 0, StaticRod), (0, 2, MovableRod)],
+            &[(0, 2)],
+        ));
+        simulation.apply(&toggle_west_rod(0, 2)).unwrap();
+        assert_eq!(
+            simulation.step(),
+            vec![SimulationEvent::ClimberStartedFalling {
+                climber: 0,
+                from: west_tile(0, 2),
+            }]
+        );
+        let events = step_while_falling(&mut simulation, 0);
+        assert_eq!(
+            events,
+            vec![SimulationEvent::ClimberLanded {
+                climber: 0,
+                on: west_tile(0, 0),
+            }]
+        );
+        assert_eq!(
+            simulation.climbers()[0].state,
+            ClimberState::Waiting {
+                on_tile: west_tile(0, 0)
+            }
+        );
+    }
+
+    #[test]
+    fn falling_climber_lands_on_an_occupied_rod() {
+        use TileDataType::*;
+        let mut simulation = Simulation::new(&level(
+            1,
+            4,
+            &[(0, 0, StaticRod), (0, 2, MovableRod)],
+            &[(0, 0), (0, 2)],
+        ));
+        simulation.apply(&toggle_west_rod(0, 2)).unwrap();
+        let events = step_while_falling(&mut simulation, 1);
+        assert_eq!(
+            events.last(),
+            Some(&SimulationEvent::ClimberLanded {
+                climber: 1,
+                on: west_tile(0, 0),
+            })
+        );
+    }
+
+    #[test]
+    fn climber_falling_to_the_ground_dies() {
+        let mut simulation =
+            Simulation::new(&level(1, 4, &[(0, 1, TileDataType::MovableRod)], &[(0, 1)]));
+        simulation.apply(&toggle_west_rod(0, 1)).unwrap();
+        let events = step_while_falling(&mut simulation, 0);
+        assert_eq!(
+            events.last(),
+            Some(&SimulationEvent::ClimberDied { climber: 0 })
+        );
+        assert_eq!(simulation.climbers()[0].state, ClimberState::Dead);
+        assert_eq!(simulation.status(), SimulationStatus::Lost);
+    }
+
+    #[test]
+    fn toggled_rod_moves_to_the_opposite_face() {
+        let mut simulation =
+            Simulation::new(&level(1, 4, &[(0, 1, TileDataType::MovableRod)], &[]));
+        assert_eq!(
+            simulation.apply(&toggle_west_rod(0, 1)),
+            Ok(vec![SimulationEvent::RodToggled {
+                pillar: 0,
+                from: FaceDirection::West,
+                to: FaceDirection::East,
+                position: TilePosition { i: 0, j: 1 },
+            }])
+        );
+        assert_eq!(simulation.rods()[0].face, FaceDirection::East);
+        let east_face = FaceId {
+            pillar: 0,
+            direction: FaceDirection::East,
+        };
+        assert!(simulation.has_ground_on_tile(&east_face, 0, 1));
+        assert!(!simulation.has_ground_on_tile(&west_tile(0, 1).face, 0, 1));
+    }
+
+    #[test]
+    fn rod_toggle_is_refused_while_the_rod_is_moving() {
+        let mut simulation =
+            Simulation::new(&level(1, 4, &[(0, 1, TileDataType::MovableRod)], &[]));
+        simulation.apply(&toggle_west_rod(0, 1)).unwrap();
+        let toggle_back = Action::ToggleRod {
+            pillar: 0,
+            face: FaceDirection::East,
+            position: TilePosition { i: 0, j: 1 },
+        };
+        assert_eq!(simulation.apply(&toggle_back), Err(ActionError::RodMoving));
+        // The refused toggle does not change anything
+        assert_eq!(simulation.rods()[0].face, FaceDirection::East);
+        step_n(&mut simulation, ROD_TOGGLE_COOLDOWN_TICKS - 1);
+        assert_eq!(simulation.apply(&toggle_back), Err(ActionError::RodMoving));
+        simulation.step();
+        assert!(simulation.apply(&toggle_back).is_ok());
+    }
+
+    #[test]
+    fn toggle_without_movable_rod_is_refused() {
+        let mut simulation = Simulation::new(&level(1, 4, &[(0, 1, TileDataType::StaticRod)], &[]));
+        assert_eq!(
+            simulation.apply(&toggle_west_rod(0, 1)),
+            Err(ActionError::NoMovableRod)
+        );
+    }
+
+    #[test]
+    fn climber_crosses_to_the_facing_face_of_a_neighbouring_pillar() {
+        use TileDataType::*;
+        // At the minimum gap, the East face of the first pillar faces the West face of the second one
+        let x = (3. * TILE_SIZE + MIN_PILLAR_GAP) / 2.;
+        let level = LevelData::test_level()
+            .with_pillar(-x, 0., 3, 3)
+            .with_tiles(FaceDirection::East, &[(2, 0, StaticRod)])
+            .with_climbers(FaceDirection::East, &[(2, 0)])
+            .with_pillar(x, 0., 3, 3)
+            .with_tiles(FaceDirection::West, &[(2, 1, StaticRod)]);
+        let mut simulation = Simulation::new(&level);
+
+        assert_eq!(
+            simulation.step(),
+            vec![SimulationEvent::ClimberMoved {
+                climber: 0,
+                from: FaceTile {
+                    face: FaceId {
+                        pillar: 0,
+                        direction: FaceDirection::East,
+                    },
+                    position: TilePosition { i: 2, j: 0 },
+                },
+                to: FaceTile {
+                    face: FaceId {
+                        pillar: 1,
+                        direction: FaceDirection::West,
+                    },
+                    position: TilePosition { i: 2, j: 1 },
+                },
+            }]
+        );
+        assert_eq!(simulation.climbers()[0].current_pillar, 1);
+    }
+}
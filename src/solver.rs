@@ -0,0 +1,300 @@
+//! Search for the shortest sequence of rod toggles winning a level.
+//!
+//! The search explores [`Simulation`] states with a 0-1 breadth-first search: toggling a rod costs one action and waiting
+//! [`SolverOptions::decision_interval`] ticks is free. The first winning state found is reached with the fewest toggles.
+//! With [`SolverOptions::exhaustive`], the player may act at every tick and exploring every reachable state without
+//! winning proves the level unsolvable.
+
+use std::{
+    collections::{HashSet, VecDeque},
+    fmt,
+};
+
+use crate::simulation::{Action, Simulation, SimulationStatus};
+
+#[derive(Clone, Debug)]
+pub struct SolverOptions {
+    /// Ticks between two moments at which the player may act. Lower is more precise but slower.
+    pub decision_interval: u32,
+    /// The search gives up after visiting this many distinct states
+    pub max_states: usize,
+}
+
+impl Default for SolverOptions {
+    fn default() -> Self {
+        Self {
+            decision_interval: 6,
+            max_states: 500_000,
+        }
+    }
+}
+
+impl SolverOptions {
+    /// Acts at every tick, so that [`SolverResult::Unsolvable`] can be returned. The cooldowns of the rods make for
+    /// many more states than the default options, each taking a few kilobytes: the search is bounded to keep the memory
+    /// in check, and reaching the bound returns [`SolverResult::Inconclusive`].
+    pub fn exhaustive() -> Self {
+        Self {
+            decision_interval: 1,
+            max_states: 200_000,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TimedAction {
+    /// Value of [`Simulation::tick`] when the action is applied
+    pub tick: u64,
+    pub action: Action,
+}
+
+impl fmt::Display for TimedAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.action {
+            Action::ToggleRod {
+                pillar,
+                face,
+                position,
+            } => write!(
+                f,
+                "tick {}: toggle the rod ({}, {}) of the {:?} face of pillar {}",
+                self.tick, position.i, position.j, face, pillar
+            ),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Solution {
+    pub actions: Vec<TimedAction>,
+    /// Tick at which the level is won when playing [`Solution::actions`]
+    pub winning_tick: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SolverResult {
+    Solved(Solution),
+    /// Every state reachable by acting only every [`SolverOptions::decision_interval`] ticks was explored without
+    /// winning. Not a proof that the level is unsolvable: acting at other ticks may still win.
+    NoSolutionAtInterval {
+        explored_states: usize,
+    },
+    /// Every reachable state was explored without winning, acting at every tick: the level can't be won. Only returned
+    /// with a [`SolverOptions::decision_interval`] of 1.
+    Unsolvable {
+        explored_states: usize,
+    },
+    /// [`SolverOptions::max_states`] was reached before finding a solution
+    Inconclusive {
+        explored_states: usize,
+    },
+}
+
+struct SearchNode {
+    parent: Option<usize>,
+    action: Option<TimedAction>,
+}
+
+/// Searches from the current state of `simulation`. Returned actions ticks are relative to `simulation`.
+pub fn solve(simulation: &Simulation, options: &SolverOptions) -> SolverResult {
+    let mut nodes = vec![SearchNode {
+        parent: None,
+        action: None,
+    }];
+    let mut visited = HashSet::new();
+    // (node index, state at this node)
+    let mut frontier = VecDeque::from([(0, simulation.clone())]);
+
+    while let Some((node_idx, state)) = frontier.pop_front() {
+        if !visited.insert(state.state_key()) {
+            continue;
+        }
+        match state.status() {
+            SimulationStatus::Won => {
+                return SolverResult::Solved(Solution {
+                    actions: collect_actions(&nodes, node_idx),
+                    winning_tick: state.tick(),
+                })
+            }
+            SimulationStatus::Lost => continue,
+            SimulationStatus::Playing => (),
+        }
+        if visited.len() >= options.max_states {
+            return SolverResult::Inconclusive {
+                explored_states: visited.len(),
+            };
+        }
+
+        // Toggling does not advance time, so that several rods can be toggled at once
+        for rod in state.rods().iter().filter(|rod| rod.cooldown_ticks == 0) {
+            let action = Action::ToggleRod {
+                pillar: rod.pillar,
+                face: rod.face.clone(),
+                position: rod.position,
+            };
+            let mut next_state = state.clone();
+            if next_state.apply(&action).is_ok() && !visited.contains(&next_state.state_key()) {
+                nodes.push(SearchNode {
+                    parent: Some(node_idx),
+                    action: Some(TimedAction {
+                        tick: state.tick(),
+                        action,
+                    }),
+                });
+                frontier.push_back((nodes.len() - 1, next_state));
+            }
+        }
+
+        let mut next_state = state;
+        for _ in 0..options.decision_interval {
+            next_state.step();
+            if next_state.status() != SimulationStatus::Playing {
+                break;
+            }
+        }
+        if !visited.contains(&next_state.state_key()) {
+            nodes.push(SearchNode {
+                parent: Some(node_idx),
+                action: None,
+            });
+            frontier.push_front((nodes.len() - 1, next_state));
+        }
+    }
+
+    let explored_states = visited.len();
+    if options.decision_interval == 1 {
+        SolverResult::Unsolvable { explored_states }
+    } else {
+        SolverResult::NoSolutionAtInterval { explored_states }
+    }
+}
+
+fn collect_actions(nodes: &[SearchNode], mut node_idx: usize) -> Vec<TimedAction> {
+    let mut actions = Vec::new();
+    loop {
+        let node = &nodes[node_idx];
+        if let Some(action) = &node.action {
+            actions.push(action.clone());
+        }
+        match node.parent {
+            Some(parent) => node_idx = parent,
+            None => break,
+        }
+    }
+    actions.reverse();
+    actions
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        data::{FaceDirection, LevelData, TileDataType},
+        simulation::{Action, Simulation, SimulationStatus, TilePosition},
+    };
+
+    use super::{solve, SolverOptions, SolverResult};
+
+    /// Gives up on a sequence of actions still not won after this many ticks
+    const MAX_TICKS: u32 = 1_000;
+
+    fn toggle(face: FaceDirection, i: u16, j: u16) -> Action {
+        Action::ToggleRod {
+            pillar: 0,
+            face,
+            position: TilePosition { i, j },
+        }
+    }
+
+    /// A 3x3 pillar with a climber in (0, 0) of its West face. It wins by toggling the East rod in (1, 1), or by
+    /// toggling the two North rods to go around the corner by the South face.
+    fn level_with_two_routes() -> LevelData {
+        use TileDataType::*;
+        LevelData::test_level()
+            .with_pillar(0., 0., 3, 3)
+            .with_tiles(FaceDirection::West, &[(0, 0, StaticRod), (2, 2, StaticRod)])
+            .with_climbers(FaceDirection::West, &[(0, 0)])
+            .with_tiles(FaceDirection::East, &[(1, 1, MovableRod)])
+            .with_tiles(
+                FaceDirection::North,
+                &[(0, 1, MovableRod), (1, 2, MovableRod)],
+            )
+    }
+
+    #[test]
+    fn solution_has_the_fewest_toggles() {
+        let level = level_with_two_routes();
+
+        // The longer route wins too
+        let mut simulation = Simulation::new(&level);
+        simulation
+            .apply(&toggle(FaceDirection::North, 0, 1))
+            .unwrap();
+        simulation
+            .apply(&toggle(FaceDirection::North, 1, 2))
+            .unwrap();
+        for _ in 0..MAX_TICKS {
+            if simulation.status() != SimulationStatus::Playing {
+                break;
+            }
+            simulation.step();
+        }
+        assert_eq!(simulation.status(), SimulationStatus::Won);
+
+        let SolverResult::Solved(solution) =
+            solve(&Simulation::new(&level), &SolverOptions::default())
+        else {
+            panic!("The level should be solved");
+        };
+        assert_eq!(
+            solution
+                .actions
+                .iter()
+                .map(|action| &action.action)
+                .collect::<Vec<_>>(),
+            vec![&toggle(FaceDirection::East, 1, 1)]
+        );
+    }
+
+    #[test]
+    fn level_without_useful_rod_has_no_solution() {
+        use TileDataType::*;
+        let level = LevelData::test_level()
+            .with_pillar(0., 0., 3, 3)
+            .with_tiles(
+                FaceDirection::West,
+                &[(0, 0, StaticRod), (2, 0, MovableRod)],
+            )
+            .with_climbers(FaceDirection::West, &[(0, 0)]);
+        assert!(matches!(
+            solve(&Simulation::new(&level), &SolverOptions::default()),
+            SolverResult::NoSolutionAtInterval { .. }
+        ));
+        assert!(matches!(
+            solve(&Simulation::new(&level), &SolverOptions::exhaustive()),
+            SolverResult::Unsolvable { .. }
+        ));
+    }
+
+    #[test]
+    fn exhaustive_search_finds_the_fewest_toggles() {
+        let SolverResult::Solved(solution) = solve(
+            &Simulation::new(&level_with_two_routes()),
+            &SolverOptions::exhaustive(),
+        ) else {
+            panic!("The level should be solved");
+        };
+        assert_eq!(solution.actions.len(), 1);
+    }
+
+    #[test]
+    fn search_stops_at_max_states() {
+        let options = SolverOptions {
+            max_states: 1,
+            ..Default::default()
+        };
+        assert_eq!(
+            solve(&Simulation::new(&level_with_two_routes()), &options),
+            SolverResult::Inconclusive { explored_states: 1 }
+        );
+    }
+}
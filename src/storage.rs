@@ -0,0 +1,75 @@
+//! Backends keeping the files written by the game across sessions.
+
+use std::{fs, io, path::PathBuf};
+
+/// Where a serialized file of the game is kept
+pub trait Storage: Send + Sync {
+    /// Returns `None` when nothing was saved yet
+    fn read(&self) -> io::Result<Option<Vec<u8>>>;
+    fn write(&mut self, bytes: &[u8]) -> io::Result<()>;
+}
+
+/// File on disk
+pub struct FileStorage {
+    path: PathBuf,
+}
+
+impl FileStorage {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    /// Returns `None` when the platform has no data folder
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn in_data_dir(file_name: &str) -> Option<Self> {
+        let dir = dirs::data_dir()?.join(env!("CARGO_PKG_NAME"));
+        Some(Self::new(dir.join(file_name)))
+    }
+}
+
+impl Storage for FileStorage {
+    fn read(&self) -> io::Result<Option<Vec<u8>>> {
+        match fs::read(&self.path) {
+            Ok(bytes) => Ok(Some(bytes)),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(error) => Err(error),
+        }
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> io::Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        // Written aside first, so that an interrupted save does not lose the previous one
+        let mut tmp_path = self.path.clone().into_os_string();
+        tmp_path.push(".tmp");
+        fs::write(&tmp_path, bytes)?;
+        fs::rename(&tmp_path, &self.path)
+    }
+}
+
+/// Lost when the game exits
+#[derive(Default)]
+pub struct MemoryStorage {
+    pub bytes: Option<Vec<u8>>,
+}
+
+impl Storage for MemoryStorage {
+    fn read(&self) -> io::Result<Option<Vec<u8>>> {
+        Ok(self.bytes.clone())
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.bytes = Some(bytes.to_vec());
+        Ok(())
+    }
+}
+
+/// `file_name` in the data folder of the platform, falling back to memory
+pub fn platform_storage(file_name: &str) -> Box<dyn Storage> {
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(storage) = FileStorage::in_data_dir(file_name) {
+        return Box::new(storage);
+    }
+    Box::<MemoryStorage>::default()
+}
@@ -0,0 +1,230 @@
+//! Command-line tools working on the level files, without starting the game.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    data::{CampaignData, LevelData},
+    generator::{generate_level, GeneratorParams},
+    simulation::Simulation,
+    solver::{solve, SolverOptions, SolverResult},
+};
+
+/// Same base folder as the Bevy asset server
+pub fn get_assets_dir() -> PathBuf {
+    std::env::var_os("CARGO_MANIFEST_DIR")
+        .map(PathBuf::from)
+        .or_else(|| {
+            std::env::current_exe()
+                .ok()
+                .and_then(|exe| exe.parent().map(Path::to_path_buf))
+        })
+        .unwrap_or_default()
+        .join("assets")
+}
+
+/// `level` is either a path to a level file, or the id of a level of the campaign at `campaign_path` (relative to the
+/// assets folder)
+pub fn load_level_from_disk(level: &str, campaign_path: &str) -> Result<LevelData, String> {
+    let level_path = if Path::new(level).is_file() {
+        PathBuf::from(level)
+    } else {
+        let campaign_path = get_assets_dir().join(campaign_path);
+        let campaign_bytes = fs::read(&campaign_path)
+            .map_err(|e| format!("failed to read {}: {e}", campaign_path.display()))?;
+        let campaign = CampaignData::from_ron_bytes(&campaign_bytes)
+            .map_err(|e| format!("failed to parse {}: {e}", campaign_path.display()))?;
+        let campaign_level = campaign
+            .chapters
+            .iter()
+            .flat_map(|chapter| chapter.levels.iter())
+            .find(|campaign_level| campaign_level.id == level)
+            .ok_or_else(|| {
+                format!("\"{level}\" is neither a level file nor a campaign level id")
+            })?;
+        campaign_path
+            .parent()
+            .unwrap_or(Path::new(""))
+            .join(&campaign_level.path)
+    };
+    load_level_file(&level_path)
+}
+
+/// Loads and checks the level file at `level_path`
+pub fn load_level_file(level_path: &Path) -> Result<LevelData, String> {
+    let bytes = fs::read(level_path)
+        .map_err(|e| format!("failed to read {}: {e}", level_path.display()))?;
+    let level_data = LevelData::from_ron_bytes(&bytes)
+        .map_err(|e| format!("failed to parse {}: {e}", level_path.display()))?;
+    level_data.check().map_err(|e| e.to_string())?;
+    Ok(level_data)
+}
+
+/// Prints the solution of `level`. When the default search finds none, an exhaustive one tries to prove the level
+/// unsolvable. Returns `false` when the level can't be loaded or solved.
+pub fn run_solver(level: &str, campaign_path: &str) -> bool {
+    let level_data = match load_level_from_disk(level, campaign_path) {
+        Ok(level_data) => level_data,
+        Err(error) => {
+            eprintln!("Error: {error}");
+            return false;
+        }
+    };
+
+    let simulation = Simulation::new(&level_data);
+    let options = SolverOptions::default();
+    // Much faster, acting every few ticks is enough for most levels. The exhaustive search only runs to prove the
+    // level unsolvable, and when it stops at its bound the default search result says more.
+    let result = match solve(&simulation, &options) {
+        SolverResult::Solved(solution) => SolverResult::Solved(solution),
+        default_result => match solve(&simulation, &SolverOptions::exhaustive()) {
+            SolverResult::Inconclusive { .. } => default_result,
+            exhaustive_result => exhaustive_result,
+        },
+    };
+    match result {
+        SolverResult::Solved(solution) => {
+            println!(
+                "Level \"{}\" solved in {} action(s), won at tick {}:",
+                level_data.name,
+                solution.actions.len(),
+                solution.winning_tick
+            );
+            for action in solution.actions.iter() {
+                println!(" - {action}");
+            }
+            true
+        }
+        SolverResult::NoSolutionAtInterval { explored_states } => {
+            println!(
+                "Level \"{}\": no solution when acting every {} ticks ({explored_states} states explored), \
+                 which does not prove the level unsolvable",
+                level_data.name, options.decision_interval
+            );
+            false
+        }
+        SolverResult::Unsolvable { explored_states } => {
+            println!(
+                "Level \"{}\" is unsolvable: no reachable state wins ({explored_states} states explored)",
+                level_data.name
+            );
+            false
+        }
+        SolverResult::Inconclusive { explored_states } => {
+            println!(
+                "Level \"{}\": no solution found within the limit of {explored_states} states",
+                level_data.name
+            );
+            false
+        }
+    }
+}
+
+/// Loads and solves every level of the campaign at `campaign_path` (relative to the assets folder), checking that its
+/// par is set and can be reached. Debug only chapters are skipped. Returns `false` when a level is invalid.
+pub fn run_validator(campaign_path: &str) -> bool {
+    let full_campaign_path = get_assets_dir().join(campaign_path);
+    let campaign = match fs::read(&full_campaign_path)
+        .map_err(|e| e.to_string())
+        .and_then(|bytes| CampaignData::from_ron_bytes(&bytes).map_err(|e| e.to_string()))
+    {
+        Ok(campaign) => campaign,
+        Err(error) => {
+            eprintln!("Error: {}: {error}", full_campaign_path.display());
+            return false;
+        }
+    };
+
+    let options = SolverOptions::default();
+    let mut valid = true;
+    for chapter in campaign
+        .chapters
+        .iter()
+        .filter(|chapter| !chapter.debug_only)
+    {
+        for campaign_level in chapter.levels.iter() {
+            let level_id = &campaign_level.id;
+            let level_data = match load_level_from_disk(level_id, campaign_path) {
+                Ok(level_data) => level_data,
+                Err(error) => {
+                    println!("Level {level_id}: {error}");
+                    valid = false;
+                    continue;
+                }
+            };
+            match solve(&Simulation::new(&level_data), &options) {
+                SolverResult::Solved(solution) => match level_data.par {
+                    Some(par) if par < solution.actions.len() => {
+                        println!(
+                            "Level {level_id}: par {par} is out of reach, the best solution takes {} action(s)",
+                            solution.actions.len()
+                        );
+                        valid = false;
+                    }
+                    None => {
+                        println!(
+                            "Level {level_id}: no par, the best solution takes {} action(s)",
+                            solution.actions.len()
+                        );
+                        valid = false;
+                    }
+                    Some(_) => println!(
+                        "Level {level_id}: ok, solved in {} action(s)",
+                        solution.actions.len()
+                    ),
+                },
+                SolverResult::NoSolutionAtInterval { explored_states } => {
+                    println!(
+                        "Level {level_id}: no solution when acting every {} ticks ({explored_states} states explored), \
+                         which does not prove the level unsolvable",
+                        options.decision_interval
+                    );
+                    valid = false;
+                }
+                SolverResult::Unsolvable { explored_states } => {
+                    println!(
+                        "Level {level_id}: unsolvable, no reachable state wins ({explored_states} states explored)"
+                    );
+                    valid = false;
+                }
+                SolverResult::Inconclusive { explored_states } => {
+                    println!("Level {level_id}: no solution found within {explored_states} states");
+                    valid = false;
+                }
+            }
+        }
+    }
+    valid
+}
+
+/// Prints the level generated from `seed` in the level file format, followed by its solution as comments
+pub fn run_generator(seed: u64) -> bool {
+    let generated = match generate_level(seed, &GeneratorParams::default()) {
+        Ok(generated) => generated,
+        Err(error) => {
+            eprintln!("Error: {error}");
+            return false;
+        }
+    };
+    let pretty_config = ron::ser::PrettyConfig::new().struct_names(false);
+    match ron::ser::to_string_pretty(&generated.level, pretty_config) {
+        Ok(level_ron) => {
+            println!("{level_ron}");
+            println!(
+                "// Solved in {} action(s), won at tick {}:",
+                generated.solution.actions.len(),
+                generated.solution.winning_tick
+            );
+            for action in generated.solution.actions.iter() {
+                println!("//  - {action}");
+            }
+            true
+        }
+        Err(error) => {
+            eprintln!("Error: {error}");
+            false
+        }
+    }
+}
@@ -0,0 +1,370 @@
+//! Texts displayed over the level, menus and keyboard controls of the game.
+
+use std::time::Duration;
+
+use bevy::{
+    ecs::{schedule::Condition, system::SystemParam},
+    prelude::{
+        default, in_state, not, resource_exists, Added, App, BuildChildren, Color, Commands,
+        Component, DetectChanges, EventReader, EventWriter, Input, IntoSystemAppConfig,
+        IntoSystemConfig, KeyCode, Local, NodeBundle, OnEnter, OnExit, OnUpdate, Plugin, Query,
+        Res, TextBundle, Visibility, With,
+    },
+    text::{Text, TextSection, TextStyle},
+    ui::{AlignItems, JustifyContent, PositionType, Size, Style, UiRect, Val},
+};
+use bevy_tweening::{lens::TextColorLens, Animator, EaseFunction, Tween};
+
+use crate::{
+    assets::GameAssets,
+    level_select::{
+        handle_level_select_buttons, populate_level_select, record_session_completion,
+        setup_level_select, toggle_level_select, SessionCompletions,
+    },
+    logic::{
+        climber::PylonPowered,
+        hint::{handle_hint_key, poll_hint_search, show_rod_hint, HintStatus, LevelHints},
+        history::{
+            handle_history_keys, sync_pylons_with_simulation, LevelHistory, SimulationRestored,
+        },
+        level::{
+            climber_event_handler, level_event_handler, LevelCompletion, LevelEvent, LevelName,
+            LevelPar, LevelSimulation, LevelStopwatch,
+        },
+        rod::RodToggled,
+    },
+    menu::{despawn_menu, handle_menu_buttons, handle_pause_key, spawn_menu},
+    replay::ReplayPlayback,
+    settings::{
+        handle_settings_buttons, populate_settings_ui, setup_settings_ui, update_settings_ui,
+    },
+    GameState,
+};
+
+#[derive(Component, Clone, Debug)]
+struct LevelNameUI;
+
+fn handle_new_levels(
+    mut new_level: Query<&LevelName, Added<LevelName>>,
+    mut level_name_ui: Query<&mut Text, With<LevelNameUI>>,
+) {
+    for loaded_level in new_level.iter_mut() {
+        let mut text = level_name_ui.single_mut();
+        text.sections.first_mut().unwrap().value = loaded_level.0.clone();
+    }
+}
+
+#[derive(Component, Clone, Debug)]
+struct HintsUI;
+
+fn update_hints_ui(hints: Res<LevelHints>, mut hints_ui: Query<&mut Text, With<HintsUI>>) {
+    if hints.is_changed() {
+        let mut text = hints_ui.single_mut();
+        text.sections.first_mut().unwrap().value = match hints.status {
+            HintStatus::None => format!("H for a hint ({} used)", hints.used),
+            HintStatus::Searching(_) => String::from("Looking for a hint..."),
+            HintStatus::Waiting(_) => String::from("Hint: wait"),
+            HintStatus::Shown => String::from("Hint: toggle the highlighted rod"),
+            HintStatus::NoMoveNeeded => {
+                String::from("Hint: no move needed, the climbers will make it")
+            }
+            HintStatus::NotFound { dead_end: true } => {
+                String::from("Hint: no solution from here, undo or restart the level")
+            }
+            HintStatus::NotFound { dead_end: false } => {
+                String::from("Hint: none found, H to try again")
+            }
+        };
+    }
+}
+
+#[derive(Component, Clone, Debug)]
+struct HudUI;
+
+/// Events changing the moves or pylons displayed by [`update_hud`]
+#[derive(SystemParam)]
+struct HudEventReaders<'w, 's> {
+    rod_toggled: EventReader<'w, 's, RodToggled>,
+    pylon_powered: EventReader<'w, 's, PylonPowered>,
+    restored: EventReader<'w, 's, SimulationRestored>,
+}
+
+/// Refreshes the moves and pylons when they change, and the time once per second
+fn update_hud(
+    stopwatch: Res<LevelStopwatch>,
+    history: Res<LevelHistory>,
+    par: Res<LevelPar>,
+    level_completion: Res<LevelCompletion>,
+    mut hud_events: HudEventReaders,
+    mut displayed_seconds: Local<Option<u64>>,
+    mut hud_ui: Query<&mut Text, With<HudUI>>,
+) {
+    // Every reader is drained, so that old events do not trigger a later refresh
+    let events_count = hud_events.rod_toggled.iter().count()
+        + hud_events.pylon_powered.iter().count()
+        + hud_events.restored.iter().count();
+    let seconds = stopwatch.0.elapsed().as_secs();
+    // A new level inserts a new par
+    if events_count == 0 && !par.is_changed() && *displayed_seconds == Some(seconds) {
+        return;
+    }
+    *displayed_seconds = Some(seconds);
+
+    let mut text = hud_ui.single_mut();
+    let moves = match par.0 {
+        Some(par) => format!("Moves: {} (par {par})", history.toggles_count()),
+        None => format!("Moves: {}", history.toggles_count()),
+    };
+    text.sections.first_mut().unwrap().value = format!(
+        "{moves}\nTime: {}:{:02}\nPylons: {}/{}",
+        seconds / 60,
+        seconds % 60,
+        level_completion.powered_pylons_count,
+        level_completion.pylons_count
+    );
+}
+
+#[derive(Component, Clone, Debug)]
+struct GameOverText;
+
+#[derive(Component, Clone, Debug)]
+struct CampaignCompletedText;
+
+fn setup_ui(mut commands: Commands, assets: Res<GameAssets>) {
+    // Dummy tween
+    let tween = Tween::new(
+        EaseFunction::QuadraticInOut,
+        Duration::from_secs(1),
+        TextColorLens {
+            start: Color::WHITE,
+            end: Color::WHITE,
+            section: 0,
+        },
+    )
+    .with_repeat_count(0);
+
+    let text_style = TextStyle {
+        font: assets.font.clone(),
+        font_size: 45.0,
+        color: Color::WHITE,
+    };
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                size: Size::width(Val::Percent(100.0)),
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_sections([TextSection::new(
+                    "A climber has fallen. Press Space to restart.",
+                    text_style.clone(),
+                )])
+                .with_style(Style {
+                    position_type: PositionType::Absolute,
+                    justify_content: JustifyContent::Center,
+                    position: UiRect {
+                        bottom: Val::Px(85.0),
+                        ..default()
+                    },
+                    ..default()
+                }),
+                GameOverText,
+                Animator::new(tween),
+            ));
+            parent.spawn((
+                TextBundle::from_sections([TextSection::new(
+                    "Every climber has been saved. Thanks for playing!",
+                    text_style.clone(),
+                )])
+                .with_style(Style {
+                    position_type: PositionType::Absolute,
+                    justify_content: JustifyContent::Center,
+                    position: UiRect {
+                        bottom: Val::Px(85.0),
+                        ..default()
+                    },
+                    ..default()
+                }),
+                CampaignCompletedText,
+            ));
+            parent.spawn((
+                TextBundle::from_sections([TextSection::new("LevelName", text_style)]).with_style(
+                    Style {
+                        position_type: PositionType::Absolute,
+                        justify_content: JustifyContent::Center,
+                        position: UiRect {
+                            top: Val::Px(15.0),
+                            ..default()
+                        },
+                        ..default()
+                    },
+                ),
+                LevelNameUI,
+            ));
+            parent.spawn((
+                TextBundle::from_sections([TextSection::new(
+                    "",
+                    TextStyle {
+                        font: assets.font.clone(),
+                        font_size: 25.0,
+                        color: Color::WHITE,
+                    },
+                )])
+                .with_style(Style {
+                    position_type: PositionType::Absolute,
+                    justify_content: JustifyContent::Center,
+                    position: UiRect {
+                        top: Val::Px(70.0),
+                        ..default()
+                    },
+                    ..default()
+                }),
+                HintsUI,
+            ));
+            parent.spawn((
+                TextBundle::from_sections([TextSection::new(
+                    "",
+                    TextStyle {
+                        font: assets.font.clone(),
+                        font_size: 25.0,
+                        color: Color::WHITE,
+                    },
+                )])
+                .with_style(Style {
+                    position_type: PositionType::Absolute,
+                    position: UiRect {
+                        top: Val::Px(15.0),
+                        left: Val::Px(15.0),
+                        ..default()
+                    },
+                    ..default()
+                }),
+                HudUI,
+            ));
+        });
+}
+
+fn hide_gameover_ui(mut game_over_ui: Query<&mut Visibility, With<GameOverText>>) {
+    let mut visibility = game_over_ui.single_mut();
+    *visibility = Visibility::Hidden;
+}
+
+fn show_campaign_completed_ui(
+    mut campaign_completed_ui: Query<&mut Visibility, With<CampaignCompletedText>>,
+) {
+    let mut visibility = campaign_completed_ui.single_mut();
+    *visibility = Visibility::Visible;
+}
+
+fn hide_campaign_completed_ui(
+    mut campaign_completed_ui: Query<&mut Visibility, With<CampaignCompletedText>>,
+) {
+    let mut visibility = campaign_completed_ui.single_mut();
+    *visibility = Visibility::Hidden;
+}
+
+fn show_gameover_ui(
+    mut game_over_ui: Query<(&mut Visibility, &mut Animator<Text>), With<GameOverText>>,
+) {
+    let (mut visibility, mut animator) = game_over_ui.single_mut();
+    *visibility = Visibility::Visible;
+
+    let tween = Tween::new(
+        EaseFunction::ExponentialInOut,
+        Duration::from_millis(1000),
+        TextColorLens {
+            start: Color::WHITE.with_a(0.),
+            end: Color::WHITE.with_a(1.),
+            section: 0,
+        },
+    );
+    animator.set_tweenable(tween);
+}
+
+fn handle_restart_key(
+    mut level_events: EventWriter<LevelEvent>,
+    keyboard_input: Res<Input<KeyCode>>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Space) {
+        level_events.send(LevelEvent::Reload);
+    }
+}
+
+/// Texts over the level, menus and overlays, and the keyboard controls. Requires the window and the other game
+/// plugins.
+pub struct GameUiPlugin;
+
+impl Plugin for GameUiPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SessionCompletions>();
+
+        app.add_startup_system(setup_ui)
+            .add_startup_system(setup_level_select)
+            .add_startup_system(setup_settings_ui);
+
+        app.add_system(
+            handle_restart_key
+                .run_if(not(in_state(GameState::MainMenu)))
+                .run_if(not(in_state(GameState::Paused))),
+        )
+        .add_system(handle_pause_key)
+        .add_system(handle_menu_buttons)
+        .add_system(populate_settings_ui)
+        .add_system(handle_settings_buttons)
+        .add_system(update_settings_ui.after(handle_settings_buttons))
+        .add_system(handle_new_levels)
+        .add_system(toggle_level_select)
+        .add_system(populate_level_select.after(toggle_level_select))
+        .add_system(record_session_completion)
+        .add_system(handle_level_select_buttons.before(level_event_handler))
+        .add_system(
+            handle_hint_key
+                .in_set(OnUpdate(GameState::Playing))
+                .run_if(resource_exists::<LevelSimulation>()),
+        )
+        .add_system(
+            poll_hint_search
+                .after(handle_hint_key)
+                .in_set(OnUpdate(GameState::Playing))
+                .run_if(resource_exists::<LevelSimulation>()),
+        )
+        .add_system(
+            show_rod_hint
+                .after(poll_hint_search)
+                .in_set(OnUpdate(GameState::Playing))
+                .run_if(resource_exists::<LevelSimulation>()),
+        )
+        .add_system(
+            handle_history_keys
+                .run_if(in_state(GameState::Playing).or_else(in_state(GameState::Lost)))
+                .run_if(resource_exists::<LevelSimulation>())
+                .run_if(not(resource_exists::<ReplayPlayback>())),
+        )
+        .add_system(update_hints_ui)
+        .add_system(
+            update_hud
+                .after(climber_event_handler)
+                .after(sync_pylons_with_simulation)
+                .run_if(resource_exists::<LevelSimulation>())
+                .run_if(resource_exists::<LevelPar>()),
+        );
+
+        app.add_system(spawn_menu.in_schedule(OnEnter(GameState::MainMenu)))
+            .add_system(hide_gameover_ui.in_schedule(OnEnter(GameState::MainMenu)))
+            .add_system(hide_campaign_completed_ui.in_schedule(OnEnter(GameState::MainMenu)))
+            .add_system(despawn_menu.in_schedule(OnExit(GameState::MainMenu)))
+            .add_system(spawn_menu.in_schedule(OnEnter(GameState::Paused)))
+            .add_system(despawn_menu.in_schedule(OnExit(GameState::Paused)))
+            .add_system(hide_gameover_ui.in_schedule(OnEnter(GameState::Playing)))
+            .add_system(show_gameover_ui.in_schedule(OnEnter(GameState::Lost)))
+            .add_system(hide_campaign_completed_ui.in_schedule(OnEnter(GameState::Playing)))
+            .add_system(
+                show_campaign_completed_ui.in_schedule(OnEnter(GameState::CampaignCompleted)),
+            );
+    }
+}